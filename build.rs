@@ -6,9 +6,9 @@ fn main() {
     let _ = dotenv::dotenv();
     println!("cargo:rerun-if-changed=.env");
 
-    let twitter_id = env::var("TWITTER_CLIENT_ID").expect("Missing env variable TWITTER_CLIENT_ID");
-    let twitter_secret =
-        env::var("TWITTER_CLIENT_SECRET").expect("Missing env variable TWITTER_CLIENT_SECRET");
+    // These are optional, the credentials can also be provided at runtime
+    let twitter_id = env::var("TWITTER_CLIENT_ID").ok();
+    let twitter_secret = env::var("TWITTER_CLIENT_SECRET").ok();
     println!("cargo:rerun-if-env-changed=TWITTER_CLIENT_ID");
     println!("cargo:rerun-if-env-changed=TWITTER_CLIENT_SECRET");
 
@@ -16,13 +16,13 @@ fn main() {
     let mut file = File::create(dest_path).unwrap();
     writeln!(
         file,
-        "const fn twitter_id() -> &'static str {{ {:?} }}",
+        "const fn twitter_id() -> Option<&'static str> {{ {:?} }}",
         twitter_id
     )
     .unwrap();
     writeln!(
         file,
-        "const fn twitter_secret() -> &'static str {{ {:?} }}",
+        "const fn twitter_secret() -> Option<&'static str> {{ {:?} }}",
         twitter_secret
     )
     .unwrap();
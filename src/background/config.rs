@@ -1,19 +1,66 @@
+use super::{credentials::TwitterCredentials, TimelineKind};
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::{Read, Write},
+    time::Duration,
 };
 
 const TARGET: &str = "config";
 
+/// The amount of tweets that are loaded at once if nothing else is configured
+const DEFAULT_PAGE_SIZE: u32 = 10;
+/// Twitter doesn't return more than this many tweets per request
+const MAX_PAGE_SIZE: u32 = 100;
+/// How close to the bottom of a list older tweets are loaded if nothing else is configured
+const DEFAULT_PREFETCH_THRESHOLD: usize = 3;
+
+/// How often timelines are checked for new tweets if nothing else is configured, in seconds
+const DEFAULT_POLL_INTERVAL: u64 = 90;
+
 #[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
 pub struct Config {
-    pub twitter: TwitterConfig,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub active_account: Option<String>,
+    /// The port of the local server that twitter redirects to after logging in
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub callback_port: Option<u16>,
+    /// The amount of tweets that are loaded at once
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub page_size: Option<u32>,
+    /// Start loading older tweets once the user scrolls to within this many tweets of the bottom
+    /// of a list
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub prefetch_threshold: Option<usize>,
+    /// Search queries that are shown in the sidebar
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub saved_searches: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub consumer: Option<ConsumerConfig>,
+    #[serde(default)]
+    pub polling: PollingConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub accounts: Vec<TwitterConfig>,
+
+    /// The single account that was stored before multiple accounts were supported.
+    /// This gets moved into `accounts` when the config is loaded.
+    #[serde(skip_serializing, default, rename = "twitter")]
+    legacy_twitter: Option<TwitterConfig>,
 }
 
 impl Config {
     pub fn load() -> Self {
         match Self::try_load() {
-            Ok(config) => config,
+            Ok(mut config) => {
+                if let Some(legacy) = config.legacy_twitter.take() {
+                    log::info!(target: TARGET, "Migrating single account config");
+                    if legacy.has_plaintext_token() {
+                        config.accounts.push(legacy);
+                    }
+                    config.save();
+                }
+                config
+            }
             Err(e) => {
                 log::warn!(
                     target: TARGET,
@@ -36,15 +83,115 @@ impl Config {
 
     pub fn save(&self) {
         let str = toml::to_string_pretty(&self).expect("Could not serialize config");
-        File::create("config.toml")
-            .unwrap()
-            .write_all(str.as_bytes())
-            .unwrap();
+        if let Err(e) = File::create("config.toml").and_then(|mut f| f.write_all(str.as_bytes())) {
+            log::error!(target: TARGET, "Could not save config: {:?}", e);
+        }
+    }
+
+    /// Find the consumer key pair of the application. In order of priority, this is taken from:
+    /// - the `[consumer]` section of the config
+    /// - the `TWITTER_CLIENT_ID` and `TWITTER_CLIENT_SECRET` environment variables
+    /// - the credentials that were provided when the application was built
+    pub fn consumer(&self) -> Option<egg_mode::KeyPair> {
+        if let Some(consumer) = &self.consumer {
+            return Some(egg_mode::KeyPair::new(
+                consumer.key.clone(),
+                consumer.secret.clone(),
+            ));
+        }
+        if let (Ok(key), Ok(secret)) = (
+            std::env::var("TWITTER_CLIENT_ID"),
+            std::env::var("TWITTER_CLIENT_SECRET"),
+        ) {
+            return Some(egg_mode::KeyPair::new(key, secret));
+        }
+        super::twitter::default_consumer()
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn prefetch_threshold(&self) -> usize {
+        self.prefetch_threshold
+            .unwrap_or(DEFAULT_PREFETCH_THRESHOLD)
+    }
+
+    pub fn account(&self, name: &str) -> Option<&TwitterConfig> {
+        self.accounts.iter().find(|a| a.name == name)
+    }
+
+    pub fn account_mut(&mut self, name: &str) -> Option<&mut TwitterConfig> {
+        self.accounts.iter_mut().find(|a| a.name == name)
+    }
+
+    /// Get the account with the given name, creating an empty one if it doesn't exist yet.
+    pub fn account_or_insert(&mut self, name: &str) -> &mut TwitterConfig {
+        if let Some(idx) = self.accounts.iter().position(|a| a.name == name) {
+            &mut self.accounts[idx]
+        } else {
+            self.accounts.push(TwitterConfig {
+                name: name.to_owned(),
+                ..Default::default()
+            });
+            self.accounts.last_mut().unwrap()
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ConsumerConfig {
+    pub key: String,
+    pub secret: String,
+}
+
+/// How often each kind of timeline is checked for new tweets, in seconds. Timelines that aren't
+/// configured use `default`, and an interval of 0 disables polling.
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+pub struct PollingConfig {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub default: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub home: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mentions: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub user: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub likes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub search: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub list: Option<u64>,
+}
+
+impl PollingConfig {
+    /// The polling interval of the given timeline, or `None` if it shouldn't be polled.
+    pub fn interval(&self, timeline: &TimelineKind) -> Option<Duration> {
+        let interval = match timeline {
+            TimelineKind::Home => self.home,
+            TimelineKind::Mentions => self.mentions,
+            TimelineKind::User(_) => self.user,
+            TimelineKind::Likes(_) => self.likes,
+            TimelineKind::Search(_) => self.search,
+            TimelineKind::List(_) => self.list,
+        };
+        match interval.or(self.default).unwrap_or(DEFAULT_POLL_INTERVAL) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
     }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
 pub struct TwitterConfig {
+    #[serde(default)]
+    pub name: String,
+
+    // Tokens used to be stored in plain text, these are only kept around until they are moved
+    // into the encrypted `Credentials`.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub access_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
@@ -54,45 +201,62 @@ pub struct TwitterConfig {
 
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub latest: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub latest_mention: Option<u64>,
+    /// Read markers of lists, by list id. TOML only supports string keys.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub latest_list: BTreeMap<String, u64>,
+    /// The timelines shown as columns, from left to right
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub columns: Vec<TimelineKind>,
 }
 
 impl TwitterConfig {
-    pub fn clear_token(&mut self) {
-        self.access_key = None;
-        self.access_secret = None;
-        self.bearer = None;
-    }
-
-    pub fn set_token(&mut self, token: &egg_mode::Token) {
-        self.clear_token();
-        match token {
-            egg_mode::Token::Access { access, .. } => {
-                self.access_key = Some(access.key.to_string());
-                self.access_secret = Some(access.secret.to_string());
-            }
-            egg_mode::Token::Bearer(string) => {
-                self.bearer = Some(string.clone());
+    /// The id of the last tweet that was read in the given timeline.
+    pub fn read_marker(&self, timeline: &TimelineKind) -> Option<u64> {
+        match timeline {
+            TimelineKind::Home => self.latest,
+            TimelineKind::Mentions => self.latest_mention,
+            TimelineKind::List(id) => self.latest_list.get(&id.to_string()).copied(),
+            _ => None,
+        }
+    }
+
+    /// Update the read marker of the given timeline, returns `false` if read markers aren't kept
+    /// for that timeline.
+    pub fn set_read_marker(&mut self, timeline: &TimelineKind, id: u64) -> bool {
+        match timeline {
+            TimelineKind::Home => self.latest = Some(id),
+            TimelineKind::Mentions => self.latest_mention = Some(id),
+            TimelineKind::List(list) => {
+                self.latest_list.insert(list.to_string(), id);
             }
+            _ => return false,
         }
+        true
     }
 
-    pub fn get_token(&self) -> Option<egg_mode::Token> {
-        if let (Some(key), Some(secret)) = (&self.access_key, &self.access_secret) {
-            Some(egg_mode::Token::Access {
-                access: egg_mode::KeyPair {
-                    key: key.clone().into(),
-                    secret: secret.clone().into(),
-                },
-                consumer: super::twitter::CONSUMER.clone(),
-            })
-        } else {
-            self.bearer.clone().map(egg_mode::Token::Bearer)
+    /// Returns `true` if this account still has a token stored in plain text.
+    pub fn has_plaintext_token(&self) -> bool {
+        (self.access_key.is_some() && self.access_secret.is_some()) || self.bearer.is_some()
+    }
+
+    /// Remove the plain text token from this account, so it can be moved into the encrypted
+    /// `Credentials`.
+    pub fn take_plaintext_token(&mut self) -> Option<TwitterCredentials> {
+        if !self.has_plaintext_token() {
+            return None;
         }
+        Some(TwitterCredentials {
+            access_key: self.access_key.take(),
+            access_secret: self.access_secret.take(),
+            bearer: self.bearer.take(),
+        })
     }
 }
 
 #[derive(Debug)]
-enum Error {
+pub enum Error {
     Io(std::io::Error),
     Toml(toml::de::Error),
 }
@@ -0,0 +1,277 @@
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::Path,
+};
+
+const TARGET: &str = "credentials";
+
+const FILE: &str = "credentials.enc";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Access tokens of all accounts, encrypted with a key derived from the passphrase of the user.
+/// This is stored in a separate file from the `Config`, which only contains non-secret settings.
+pub struct Credentials {
+    key: [u8; 32],
+    salt: [u8; SALT_LEN],
+    contents: Contents,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct Contents {
+    #[serde(default)]
+    accounts: BTreeMap<String, TwitterCredentials>,
+}
+
+/// The on-disk format of the credentials file. All fields are base64 encoded.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EncryptedFile {
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+impl Credentials {
+    pub fn exists() -> bool {
+        Path::new(FILE).exists()
+    }
+
+    /// Create a new, empty credentials store. This will not be written to disk until `save` is
+    /// called.
+    pub fn create(passphrase: &str) -> Result<Self, Error> {
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Ok(Self {
+            key: derive_key(passphrase, &salt)?,
+            salt,
+            contents: Contents::default(),
+        })
+    }
+
+    pub fn open(passphrase: &str) -> Result<Self, Error> {
+        Self::open_from(Path::new(FILE), passphrase)
+    }
+
+    fn open_from(path: &Path, passphrase: &str) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let file: EncryptedFile = toml::from_str(&content)?;
+
+        let salt: [u8; SALT_LEN] = base64::decode(&file.salt)?
+            .try_into()
+            .map_err(|_| Error::Corrupt)?;
+        let nonce = base64::decode(&file.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(Error::Corrupt);
+        }
+        let data = base64::decode(&file.data)?;
+
+        let key = derive_key(passphrase, &salt)?;
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(Nonce::from_slice(&nonce), data.as_slice())
+            .map_err(|_| Error::WrongPassphrase)?;
+        let contents = std::str::from_utf8(&plaintext).map_err(|_| Error::Corrupt)?;
+        Ok(Self {
+            key,
+            salt,
+            contents: toml::from_str(contents)?,
+        })
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = self.try_save() {
+            log::error!(target: TARGET, "Could not save credentials: {:?}", e);
+        }
+    }
+
+    fn try_save(&self) -> Result<(), Error> {
+        self.save_to(Path::new(FILE))
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), Error> {
+        let plaintext = toml::to_string(&self.contents).expect("Could not serialize credentials");
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let data = ChaCha20Poly1305::new(Key::from_slice(&self.key))
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+            .map_err(|_| Error::Encrypt)?;
+        let file = EncryptedFile {
+            salt: base64::encode(self.salt),
+            nonce: base64::encode(nonce),
+            data: base64::encode(data),
+        };
+        let str = toml::to_string_pretty(&file).expect("Could not serialize credentials");
+
+        // Write to a temporary file first so a crash can't leave us with a half-written file
+        let tmp = format!("{}.tmp", path.display());
+        create_private(&tmp)?.write_all(str.as_bytes())?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Delete the saved credentials, for when the user forgot their passphrase
+    pub fn forget() -> Result<(), Error> {
+        match fs::remove_file(FILE) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn account(&self, name: &str) -> Option<&TwitterCredentials> {
+        self.contents.accounts.get(name)
+    }
+
+    pub fn account_or_insert(&mut self, name: &str) -> &mut TwitterCredentials {
+        self.contents.accounts.entry(name.to_owned()).or_default()
+    }
+
+    pub fn remove_account(&mut self, name: &str) -> Option<TwitterCredentials> {
+        self.contents.accounts.remove(name)
+    }
+
+    pub fn rename_account(&mut self, from: &str, to: &str) {
+        if let Some(credentials) = self.contents.accounts.remove(from) {
+            self.contents.accounts.insert(to.to_owned(), credentials);
+        }
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], Error> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| Error::KeyDerivation)?;
+    Ok(key)
+}
+
+/// Create a file that is only readable by the current user
+fn create_private(path: &str) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // `mode` is only applied when the file is created
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    options.open(path)
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+pub struct TwitterCredentials {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub access_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub access_secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bearer: Option<String>,
+}
+
+impl TwitterCredentials {
+    pub fn clear_token(&mut self) {
+        self.access_key = None;
+        self.access_secret = None;
+        self.bearer = None;
+    }
+
+    pub fn set_token(&mut self, token: &egg_mode::Token) {
+        self.clear_token();
+        match token {
+            egg_mode::Token::Access { access, .. } => {
+                self.access_key = Some(access.key.to_string());
+                self.access_secret = Some(access.secret.to_string());
+            }
+            egg_mode::Token::Bearer(string) => {
+                self.bearer = Some(string.clone());
+            }
+        }
+    }
+
+    pub fn get_token(&self, consumer: &egg_mode::KeyPair) -> Option<egg_mode::Token> {
+        if let (Some(key), Some(secret)) = (&self.access_key, &self.access_secret) {
+            Some(egg_mode::Token::Access {
+                access: egg_mode::KeyPair {
+                    key: key.clone().into(),
+                    secret: secret.clone().into(),
+                },
+                consumer: consumer.clone(),
+            })
+        } else {
+            self.bearer.clone().map(egg_mode::Token::Bearer)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Base64(base64::DecodeError),
+    Corrupt,
+    KeyDerivation,
+    Encrypt,
+    WrongPassphrase,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(fmt, "Could not access {}: {}", FILE, e),
+            Self::Toml(e) => write!(fmt, "{} is corrupt: {}", FILE, e),
+            Self::Base64(e) => write!(fmt, "{} is corrupt: {}", FILE, e),
+            Self::Corrupt => write!(fmt, "{} is corrupt", FILE),
+            Self::KeyDerivation => write!(fmt, "Could not derive a key from the passphrase"),
+            Self::Encrypt => write!(fmt, "Could not encrypt the credentials"),
+            Self::WrongPassphrase => write!(fmt, "Wrong passphrase"),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(io: std::io::Error) -> Self {
+        Self::Io(io)
+    }
+}
+impl From<toml::de::Error> for Error {
+    fn from(toml: toml::de::Error) -> Self {
+        Self::Toml(toml)
+    }
+}
+impl From<base64::DecodeError> for Error {
+    fn from(base64: base64::DecodeError) -> Self {
+        Self::Base64(base64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("credentials_{}.enc", std::process::id()));
+        let mut credentials = Credentials::create("correct horse").unwrap();
+        credentials.account_or_insert("alice").bearer = Some(String::from("token"));
+        credentials.save_to(&path).unwrap();
+
+        let wrong = Credentials::open_from(&path, "wrong horse");
+        let right = Credentials::open_from(&path, "correct horse");
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(wrong, Err(Error::WrongPassphrase)));
+        let bearer = right.unwrap().account("alice").unwrap().bearer.clone();
+        assert_eq!(bearer.as_deref(), Some("token"));
+    }
+}
@@ -0,0 +1,235 @@
+use super::{
+    config::Error,
+    post::{Attachment, NewTweet},
+};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{Read, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const TARGET: &str = "drafts";
+
+/// Stored next to `config.toml`
+const FILE_NAME: &str = "drafts.toml";
+
+/// A tweet that hasn't been posted yet
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Draft {
+    pub id: u64,
+    /// When the draft was last changed, as a unix timestamp
+    pub saved_at: u64,
+    pub text: String,
+    /// The tweets of a thread, this replaces `text` when it isn't empty
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub thread: Vec<String>,
+    /// The ids of the tweets of the thread that were posted already, in order
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub posted: Vec<u64>,
+    /// Attached to the first tweet of a thread
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub media: Vec<Attachment>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reply: Option<ReplyTarget>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub quote: Option<QuotedTweet>,
+}
+
+/// The tweet a draft replies to
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ReplyTarget {
+    pub id: u64,
+    pub screen_name: String,
+    pub text: String,
+    /// The other users in the conversation, and whether they will be mentioned
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub mentions: Vec<Mention>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Mention {
+    pub id: u64,
+    pub screen_name: String,
+    pub selected: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QuotedTweet {
+    pub id: u64,
+    pub screen_name: String,
+    pub text: String,
+}
+
+impl Draft {
+    pub fn new() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            // Drafts are created by hand, so they are never started in the same millisecond
+            id: now.as_millis() as u64,
+            saved_at: now.as_secs(),
+            text: String::new(),
+            thread: Vec::new(),
+            posted: Vec::new(),
+            media: Vec::new(),
+            reply: None,
+            quote: None,
+        }
+    }
+
+    /// There is nothing in the draft worth keeping
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+            && self.thread.iter().all(|t| t.trim().is_empty())
+            && self.media.is_empty()
+            && self.reply.is_none()
+            && self.quote.is_none()
+    }
+
+    pub fn tweet(&self) -> NewTweet {
+        NewTweet {
+            text: self.text.clone(),
+            in_reply_to: self.reply.as_ref().map(|r| r.id),
+            exclude_reply_user_ids: self
+                .reply
+                .iter()
+                .flat_map(|r| &r.mentions)
+                .filter(|m| !m.selected)
+                .map(|m| m.id)
+                .collect(),
+            quote_url: self.quote.as_ref().map(|quote| {
+                format!(
+                    "https://twitter.com/{}/status/{}",
+                    quote.screen_name, quote.id
+                )
+            }),
+            media: self.media.clone(),
+        }
+    }
+
+    /// The tweets of the thread that haven't been posted yet. The first one continues the thread
+    /// after the last tweet that was posted, the runner chains the others.
+    pub fn thread_tweets(&self) -> Vec<NewTweet> {
+        let count = self.thread.len();
+        let start = self.posted.len();
+        let first = self.tweet();
+        self.thread
+            .iter()
+            .enumerate()
+            .skip(start)
+            .map(|(index, text)| {
+                let text = numbered(text, index, count);
+                if index == 0 {
+                    // The first tweet is the one that replies, quotes and has the attachments
+                    NewTweet {
+                        text,
+                        ..first.clone()
+                    }
+                } else {
+                    // Every tweet replies to the conversation, so the mentions the user
+                    // deselected would be added back otherwise
+                    NewTweet {
+                        text,
+                        in_reply_to: self.posted.last().copied().filter(|_| index == start),
+                        exclude_reply_user_ids: first.exclude_reply_user_ids.clone(),
+                        ..Default::default()
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// The text of the tweet at `index` of a thread, with its number added
+pub fn numbered(text: &str, index: usize, count: usize) -> String {
+    format!("{} {}/{}", text.trim_end(), index + 1, count)
+}
+
+impl Default for Draft {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The drafts of every account, by the name of the account
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+pub struct Drafts {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    accounts: BTreeMap<String, Vec<Draft>>,
+}
+
+impl Drafts {
+    pub fn load() -> Self {
+        match Self::try_load() {
+            Ok(drafts) => drafts,
+            Err(e) => {
+                log::info!(target: TARGET, "No drafts loaded ({:?})", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn try_load() -> Result<Self, Error> {
+        let mut file = File::open(FILE_NAME)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        toml::from_str(&content).map_err(Into::into)
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.try_save() {
+            log::error!(target: TARGET, "Could not save drafts: {:?}", e);
+        }
+    }
+
+    fn try_save(&self) -> std::io::Result<()> {
+        let str = toml::to_string_pretty(&self).expect("Could not serialize drafts");
+        // Write to a temporary file first so a crash can't leave us with a half-written file
+        let tmp = format!("{}.tmp", FILE_NAME);
+        File::create(&tmp)?.write_all(str.as_bytes())?;
+        fs::rename(&tmp, FILE_NAME)
+    }
+
+    /// The drafts of an account, the most recently changed first
+    pub fn get(&self, account: &str) -> Vec<Draft> {
+        let mut drafts = self.accounts.get(account).cloned().unwrap_or_default();
+        drafts.sort_by_key(|d| std::cmp::Reverse(d.saved_at));
+        drafts
+    }
+
+    /// Add the draft, or replace the one with the same id
+    pub fn store(&mut self, account: &str, draft: Draft) {
+        let drafts = self.accounts.entry(account.to_owned()).or_default();
+        match drafts.iter_mut().find(|d| d.id == draft.id) {
+            Some(existing) if *existing == draft => return,
+            Some(existing) => *existing = draft,
+            None => drafts.push(draft),
+        }
+        self.save();
+    }
+
+    /// Remember that a tweet of the thread of the draft was posted, so it's not posted again
+    pub fn add_posted(&mut self, account: &str, id: u64, tweet_id: u64) {
+        let drafts = self.accounts.get_mut(account);
+        if let Some(draft) = drafts.and_then(|drafts| drafts.iter_mut().find(|d| d.id == id)) {
+            draft.posted.push(tweet_id);
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, account: &str, id: u64) {
+        if let Some(drafts) = self.accounts.get_mut(account) {
+            let len = drafts.len();
+            drafts.retain(|d| d.id != id);
+            if drafts.len() == len {
+                return;
+            }
+            if drafts.is_empty() {
+                self.accounts.remove(account);
+            }
+            self.save();
+        }
+    }
+}
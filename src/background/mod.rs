@@ -1,18 +1,51 @@
 mod config;
+mod credentials;
+mod drafts;
+mod post;
+mod preview;
+mod rate_limit;
+mod schedule;
+mod timeline;
 pub mod twitter;
 
-use self::config::Config;
+use self::{
+    config::{Config, ConsumerConfig},
+    credentials::Credentials,
+    drafts::Drafts,
+    post::PostError,
+    rate_limit::{RateLimits, POST_ENDPOINTS},
+    schedule::Schedule,
+    timeline::{Direction, Timeline},
+};
+pub use self::{
+    drafts::{numbered, Draft, Mention, QuotedTweet, ReplyTarget},
+    post::{Attachment, MediaKind, NewTweet, TweetAction, UploadStage, MAX_IMAGES},
+    preview::Preview,
+    rate_limit::unix_now,
+    schedule::ScheduledTweet,
+    timeline::{Gap, TimelineKind},
+};
 use egui_with_background::{image, winit::EventLoopProxy};
-use std::time::Instant;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use tokio::sync::mpsc::{
     unbounded_channel, UnboundedReceiver as Receiver, UnboundedSender as Sender,
 };
 
 const TARGET: &str = "Background";
 
+/// Replies are walked up one request at a time, stop after this many
+const MAX_ANCESTORS: usize = 20;
+/// The amount of recent replies to a user that are searched for replies to a tweet
+const REPLY_SEARCH_SIZE: u32 = 100;
+
 pub fn spawn(proxy: EventLoopProxy<ToUI>) -> Background {
     // let (to_ui, from_ui) = unbounded_channel::<ToUI>();
     let (to_backend, from_backend) = unbounded_channel::<ToBackground>();
+    let to_self = to_backend.clone();
     std::thread::spawn(move || {
         let runtime = tokio::runtime::Builder::new_current_thread()
             .enable_io()
@@ -21,7 +54,7 @@ pub fn spawn(proxy: EventLoopProxy<ToUI>) -> Background {
             .build()
             .unwrap();
         runtime.block_on(async move {
-            let runner = Runner::new(proxy.clone(), from_backend).await;
+            let runner = Runner::new(proxy.clone(), to_self, from_backend).await;
             if let Err(e) = runner.run().await {
                 log::error!(target: TARGET, "Crashed: {:?}", e);
             }
@@ -55,20 +88,108 @@ impl Background {
         self.send(ToBackground::TwitterPin { pin });
     }
 
-    pub fn load_homepage(&self) {
-        self.send(ToBackground::LoadInitialTweets);
+    pub fn cancel_login(&self) {
+        self.send(ToBackground::CancelLogin);
+    }
+
+    pub fn unlock_credentials(&self, passphrase: String) {
+        self.send(ToBackground::UnlockCredentials { passphrase });
+    }
+
+    pub fn forget_credentials(&self) {
+        self.send(ToBackground::ForgetCredentials);
+    }
+
+    pub fn set_consumer(&self, key: String, secret: String) {
+        self.send(ToBackground::SetConsumer { key, secret });
+    }
+
+    pub fn browse_as_guest(&self) {
+        self.send(ToBackground::BrowseAsGuest);
+    }
+
+    pub fn load_initial(&self, timeline: TimelineKind) {
+        self.send(ToBackground::LoadInitialTweets { timeline });
+    }
+    pub fn load_newer(&self, timeline: TimelineKind) {
+        self.send(ToBackground::LoadNewerTweets { timeline });
+    }
+    pub fn load_older(&self, timeline: TimelineKind) {
+        self.send(ToBackground::LoadOlderTweets { timeline });
+    }
+    pub fn search(&self, query: String) {
+        self.send(ToBackground::Search { query });
+    }
+    pub fn save_search(&self, query: String) {
+        self.send(ToBackground::SaveSearch { query });
+    }
+    pub fn remove_saved_search(&self, query: String) {
+        self.send(ToBackground::RemoveSavedSearch { query });
+    }
+    pub fn load_lists(&self) {
+        self.send(ToBackground::LoadLists);
+    }
+    pub fn load_gap(&self, timeline: TimelineKind, gap: Gap) {
+        self.send(ToBackground::LoadGap { timeline, gap });
+    }
+    /// Post the tweet of the draft with the given id, the draft is deleted once it's posted
+    pub fn post_tweet(&self, draft: u64, tweet: NewTweet) {
+        self.send(ToBackground::PostTweet { draft, tweet });
+    }
+    /// Post the tweets of the thread of a draft as a chain of replies to the first one
+    pub fn post_thread(&self, draft: u64, tweets: Vec<NewTweet>) {
+        self.send(ToBackground::PostThread { draft, tweets });
+    }
+    /// Store the draft, replacing an earlier version of it
+    pub fn save_draft(&self, draft: Draft) {
+        self.send(ToBackground::SaveDraft { draft });
+    }
+    pub fn delete_draft(&self, id: u64) {
+        self.send(ToBackground::DeleteDraft { id });
+    }
+    /// Post the tweet at its time, replacing an earlier version of it
+    pub fn schedule_tweet(&self, tweet: ScheduledTweet) {
+        self.send(ToBackground::ScheduleTweet { tweet });
+    }
+    pub fn cancel_scheduled_tweet(&self, id: u64) {
+        self.send(ToBackground::CancelScheduledTweet { id });
+    }
+    pub fn tweet_action(&self, id: u64, action: TweetAction) {
+        self.send(ToBackground::TweetAction { id, action });
+    }
+    pub fn load_conversation(&self, tweet: egg_mode::tweet::Tweet) {
+        self.send(ToBackground::LoadConversation {
+            tweet: Box::new(tweet),
+        });
+    }
+    /// Decode a preview of an image that is attached to a tweet
+    pub fn load_preview(&self, path: PathBuf) {
+        self.send(ToBackground::LoadPreview { path });
+    }
+    pub fn load_profile(&self, screen_name: String) {
+        self.send(ToBackground::LoadProfile { screen_name });
     }
-    pub fn load_newer(&self) {
-        self.send(ToBackground::LoadNewerTweets);
+    pub fn set_columns(&self, columns: Vec<TimelineKind>) {
+        self.send(ToBackground::SetColumns { columns });
     }
-    pub fn load_older(&self) {
-        self.send(ToBackground::LoadOlderTweets);
+    pub fn set_latest_tweet(&self, timeline: TimelineKind, id: u64) {
+        self.send(ToBackground::SetLatestTweet { timeline, id });
     }
-    pub fn set_latest_tweet(&self, id: u64) {
-        self.send(ToBackground::SetLatestTweet { id });
+
+    pub fn switch_account(&self, name: String) {
+        self.send(ToBackground::SwitchAccount { name });
+    }
+    pub fn add_account(&self) {
+        self.send(ToBackground::AddAccount);
     }
 
-    pub fn logout(&self) {}
+    pub fn logout(&self) {
+        self.send(ToBackground::Logout);
+    }
+    /// Stop browsing as a guest and go back to the login screen
+    pub fn leave_guest(&self) {
+        self.send(ToBackground::LeaveGuest);
+    }
 }
 
 impl egui_with_background::Background for Background {
@@ -84,33 +205,383 @@ impl egui_with_background::Background for Background {
 struct Runner {
     sender: EventLoopProxy<ToUI>,
     receiver: Receiver<ToBackground>,
+    /// Used by spawned tasks to send messages back to the runner
+    to_self: Sender<ToBackground>,
     running: bool,
     config: Config,
+    drafts: Drafts,
+    schedule: Schedule,
+    /// `None` until the user entered their passphrase
+    credentials: Option<Credentials>,
     state: BackgroundState,
+    sessions: Vec<LoggedIn>,
+    callback_listener: Option<tokio::task::JoinHandle<()>>,
     last_new_version_check: Instant,
+    /// The UI shows how long we're rate limited, it's repainted every second while we are
+    last_countdown_tick: Instant,
+    /// A scheduled tweet is being posted
+    posting_scheduled: bool,
 }
 
 impl Runner {
-    async fn new(sender: EventLoopProxy<ToUI>, receiver: Receiver<ToBackground>) -> Self {
+    async fn new(
+        sender: EventLoopProxy<ToUI>,
+        to_self: Sender<ToBackground>,
+        receiver: Receiver<ToBackground>,
+    ) -> Self {
         let config = Config::load();
         let mut result = Self {
             sender,
             receiver,
+            to_self,
             running: true,
             config,
+            drafts: Drafts::load(),
+            schedule: Schedule::load(),
+            credentials: None,
             state: BackgroundState::NotLoggedIn,
+            sessions: Vec::new(),
+            callback_listener: None,
             last_new_version_check: Instant::now(),
+            last_countdown_tick: Instant::now(),
+            posting_scheduled: false,
         };
-        if let Some(token) = result.config.twitter.get_token() {
-            result.login_from_token(token).await;
-        }
+        result.send_to_ui(ToUI::CredentialsLocked {
+            exists: Credentials::exists(),
+        });
         check_for_new_version(result.sender.clone());
         result
     }
 
-    async fn login_from_token(&mut self, token: egg_mode::Token) {
+    /// Delete the saved logins, the user chooses a new passphrase next
+    fn forget_credentials(&mut self) {
+        if self.credentials.is_some() {
+            log::warn!(target: TARGET, "Credentials are unlocked, not forgetting them");
+            return;
+        }
+        if let Err(e) = Credentials::forget() {
+            log::warn!(target: TARGET, "Could not forget credentials: {:?}", e);
+            self.send_to_ui(ToUI::Error {
+                error: e.to_string(),
+            });
+            return;
+        }
+        log::info!(target: TARGET, "Forgot saved logins");
+        self.send_to_ui(ToUI::CredentialsLocked { exists: false });
+    }
+
+    /// Open the encrypted credentials, or create them if they don't exist yet, and log in to all
+    /// accounts that have a token.
+    async fn unlock_credentials(&mut self, passphrase: String) {
+        let result = if Credentials::exists() {
+            Credentials::open(&passphrase)
+        } else {
+            Credentials::create(&passphrase)
+        };
+        let mut credentials = match result {
+            Ok(credentials) => credentials,
+            Err(e) => {
+                log::warn!(target: TARGET, "Could not unlock credentials: {:?}", e);
+                self.send_to_ui(ToUI::Error {
+                    error: e.to_string(),
+                });
+                return;
+            }
+        };
+
+        // Move any tokens that are still stored in plain text into the encrypted credentials
+        let mut migrated = false;
+        for account in &mut self.config.accounts {
+            if let Some(token) = account.take_plaintext_token() {
+                log::info!(target: TARGET, "Encrypting token of {:?}", account.name);
+                *credentials.account_or_insert(&account.name) = token;
+                migrated = true;
+            }
+        }
+        // Always save, so the file exists for the next launch
+        credentials.save();
+        if migrated {
+            self.config.save();
+        }
+
+        self.credentials = Some(credentials);
+        self.send_to_ui(ToUI::CredentialsUnlocked);
+        self.login_from_config().await;
+    }
+
+    async fn login_from_config(&mut self) {
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials,
+            None => return,
+        };
+        let consumer = match self.config.consumer() {
+            Some(consumer) => consumer,
+            None => {
+                log::warn!(target: TARGET, "No consumer key pair configured");
+                self.send_to_ui(ToUI::ConsumerMissing);
+                return;
+            }
+        };
+        let tokens: Vec<_> = self
+            .config
+            .accounts
+            .iter()
+            .filter_map(|a| {
+                let token = credentials.account(&a.name)?.get_token(&consumer)?;
+                Some((a.name.clone(), token))
+            })
+            .collect();
+        if tokens.is_empty() {
+            return;
+        }
         self.send_to_ui(ToUI::Loading);
-        self.handle_login_result(twitter::User::login_with_token(token).await);
+        for (name, token) in tokens {
+            match twitter::User::login_with_token(token).await {
+                Err(e) if twitter::is_unauthorized(&e) => {
+                    log::warn!(target: TARGET, "Token of {:?} is no longer valid", name);
+                    if let Some(credentials) = &mut self.credentials {
+                        credentials.remove_account(&name);
+                    }
+                    self.send_to_ui(ToUI::Error {
+                        error: format!("The session of {} has expired, please log in again", name),
+                    });
+                }
+                Ok(user) => {
+                    if name != user.name {
+                        // Accounts migrated from an older config don't have a name yet
+                        if let Some(account) = self.config.account_mut(&name) {
+                            account.name = user.name.clone();
+                        }
+                        if let Some(credentials) = &mut self.credentials {
+                            credentials.rename_account(&name, &user.name);
+                        }
+                    }
+                    self.add_session(user);
+                }
+                Err(e) => {
+                    log::warn!(target: TARGET, "Could not log in {:?}: {:?}", name, e);
+                    self.send_to_ui(ToUI::Error {
+                        error: format!("Could not log in {}: {}", name, e),
+                    });
+                }
+            }
+        }
+        self.config.save();
+        if let Some(credentials) = &self.credentials {
+            credentials.save();
+        }
+
+        let active = self
+            .config
+            .active_account
+            .as_deref()
+            .and_then(|name| self.find_session(name))
+            .unwrap_or(0);
+        if active < self.sessions.len() {
+            self.activate_session(active);
+        }
+    }
+
+    /// Add a session for the given user, replacing the existing one if that user is already
+    /// logged in. Returns the index of the session.
+    fn add_session(&mut self, user: twitter::User) -> usize {
+        let existing = if user.is_guest() {
+            self.sessions.iter().position(|s| s.user.is_guest())
+        } else {
+            self.find_session(&user.name)
+        };
+        let session = LoggedIn {
+            user,
+            timelines: HashMap::new(),
+            columns: Vec::new(),
+            rate_limits: RateLimits::default(),
+            queued: Vec::new(),
+        };
+        if let Some(idx) = existing {
+            self.sessions[idx] = session;
+            idx
+        } else {
+            self.sessions.push(session);
+            self.sessions.len() - 1
+        }
+    }
+
+    fn activate_session(&mut self, idx: usize) {
+        let user = self.sessions[idx].user.clone();
+        self.state = BackgroundState::LoggedIn(idx);
+        if !user.is_guest() && self.config.active_account.as_ref() != Some(&user.name) {
+            self.config.active_account = Some(user.name.clone());
+            self.config.save();
+        }
+        let accounts = self.account_names();
+        let columns = self
+            .config
+            .account(&user.name)
+            .filter(|_| !user.is_guest())
+            .map(|a| a.columns.clone())
+            .unwrap_or_default();
+        let prefetch_threshold = self.config.prefetch_threshold();
+        self.send_to_ui(ToUI::LoggedIn {
+            user,
+            accounts,
+            columns,
+            prefetch_threshold,
+        });
+        self.send_saved_searches();
+        self.send_drafts();
+        self.send_scheduled_tweets();
+        self.send_rate_limits();
+    }
+
+    fn send_rate_limits(&mut self) {
+        if let Some(session) = self.session_mut() {
+            let limits = session.rate_limits.all();
+            self.send_to_ui(ToUI::RateLimits { limits });
+        }
+    }
+
+    /// Remember the rate limit status of a request made by the active session
+    fn update_rate_limit(&mut self, endpoint: &'static str, status: egg_mode::RateLimit) {
+        if let Some(session) = self.session_mut() {
+            session.rate_limits.update(endpoint, status);
+        }
+        self.send_rate_limits();
+    }
+
+    fn send_drafts(&mut self) {
+        let drafts = match self.active_account() {
+            Some(name) => self.drafts.get(&name),
+            None => Vec::new(),
+        };
+        self.send_to_ui(ToUI::Drafts { drafts });
+    }
+
+    fn send_scheduled_tweets(&mut self) {
+        let tweets = match self.active_account() {
+            Some(name) => self.schedule.get(&name),
+            None => Vec::new(),
+        };
+        self.send_to_ui(ToUI::ScheduledTweets { tweets });
+    }
+
+    /// When the next scheduled tweet of a logged in account should be posted
+    fn next_scheduled(&self) -> Option<Instant> {
+        if self.posting_scheduled {
+            return None;
+        }
+        let (account, tweet) = self
+            .schedule
+            .next(|name| self.find_session(name).is_some())?;
+        let wait = tweet.post_at.saturating_sub(rate_limit::unix_now());
+        let due = Instant::now() + Duration::from_secs(wait);
+        let idx = self.find_session(account)?;
+        let available_at = self.sessions[idx]
+            .rate_limits
+            .all_available_at(&POST_ENDPOINTS);
+        Some(available_at.map_or(due, |at| at.max(due)))
+    }
+
+    fn send_saved_searches(&mut self) {
+        let queries = self.config.saved_searches.clone();
+        self.send_to_ui(ToUI::SavedSearches { queries });
+    }
+
+    async fn logout(&mut self) {
+        let idx = match self.state {
+            BackgroundState::LoggedIn(idx) => idx,
+            _ => {
+                log::warn!(target: TARGET, "Not logged in, ignoring logout");
+                return;
+            }
+        };
+        if let Err(e) = self.sessions[idx].user.invalidate_token().await {
+            // The token is removed locally either way
+            log::warn!(target: TARGET, "Could not invalidate token: {:?}", e);
+        }
+        self.end_session(idx, None);
+    }
+
+    fn leave_guest(&mut self) {
+        match self.state {
+            BackgroundState::LoggedIn(idx) if self.sessions[idx].user.is_guest() => {
+                self.end_session(idx, None)
+            }
+            _ => log::warn!(target: TARGET, "Not browsing as guest, ignoring"),
+        }
+    }
+
+    /// Remove the session at the given index, forget its token and send the UI back to the login
+    /// screen. Guests have nothing stored, and their name may be the name of an actual account.
+    fn end_session(&mut self, idx: usize, message: Option<String>) {
+        let session = self.sessions.remove(idx);
+        if !session.user.is_guest() {
+            if let Some(credentials) = &mut self.credentials {
+                credentials.remove_account(&session.user.name);
+                credentials.save();
+            }
+            if self.config.active_account.as_ref() == Some(&session.user.name) {
+                self.config.active_account = None;
+                self.config.save();
+            }
+        }
+
+        self.state = BackgroundState::NotLoggedIn;
+        let accounts = self.account_names();
+        self.send_to_ui(ToUI::LoggedOut { accounts, message });
+        // The login screen starts over, guests may not have unlocked or configured anything yet
+        if self.credentials.is_none() {
+            self.send_to_ui(ToUI::CredentialsLocked {
+                exists: Credentials::exists(),
+            });
+        }
+        if self.config.consumer().is_none() {
+            self.send_to_ui(ToUI::ConsumerMissing);
+        }
+    }
+
+    /// Called when the API tells us the token of the active session is revoked or expired.
+    fn session_expired(&mut self) {
+        if let BackgroundState::LoggedIn(idx) = self.state {
+            let name = self.sessions[idx].user.name.clone();
+            log::warn!(target: TARGET, "Token of {:?} is no longer valid", name);
+            self.end_session(
+                idx,
+                Some(format!(
+                    "The session of {} has expired, please log in again",
+                    name
+                )),
+            );
+        }
+    }
+
+    /// Find the session of the account with the given name. This never returns the guest session.
+    fn find_session(&self, name: &str) -> Option<usize> {
+        self.sessions
+            .iter()
+            .position(|s| !s.user.is_guest() && s.user.name == name)
+    }
+
+    fn account_names(&self) -> Vec<String> {
+        self.sessions
+            .iter()
+            .filter(|s| !s.user.is_guest())
+            .map(|s| s.user.name.clone())
+            .collect()
+    }
+
+    /// The name of the account that is logged in, `None` for guests
+    fn active_account(&mut self) -> Option<String> {
+        match self.session_mut() {
+            Some(session) if !session.user.is_guest() => Some(session.user.name.clone()),
+            _ => None,
+        }
+    }
+
+    fn session_mut(&mut self) -> Option<&mut LoggedIn> {
+        match self.state {
+            BackgroundState::LoggedIn(idx) => self.sessions.get_mut(idx),
+            _ => None,
+        }
     }
 
     async fn run(mut self) -> Result<(), ()> {
@@ -121,13 +592,44 @@ impl Runner {
                 check_for_new_version(self.sender.clone());
             }
 
+            let auth_expires_at = match &self.state {
+                BackgroundState::Authing(request) => Some(request.expires_at()),
+                _ => None,
+            };
+            let next_poll = self.session_mut().and_then(|s| s.next_poll());
+            let next_queued = self.session_mut().and_then(|s| s.next_queued());
+            let next_scheduled = self.next_scheduled();
+            let next_countdown_tick = match self.session_mut() {
+                Some(session) if session.rate_limits.any_exhausted() => {
+                    Some(self.last_countdown_tick + Duration::from_secs(1))
+                }
+                _ => None,
+            };
+
             tokio::select! {
                 msg = self.receiver.recv() => self.handle_recv(msg).await,
+                _ = sleep_until(next_poll) => self.poll_timelines().await,
+                _ = sleep_until(next_queued) => self.run_queued().await,
+                _ = sleep_until(next_scheduled) => self.post_scheduled(),
+                _ = sleep_until(next_countdown_tick) => {
+                    self.last_countdown_tick = Instant::now();
+                    self.send_to_ui(ToUI::Repaint);
+                }
+                _ = sleep_until(auth_expires_at) => {
+                    log::info!(target: TARGET, "Login request expired");
+                    self.cancel_login();
+                    self.send_login_error(twitter::LoginError::TimedOut);
+                }
             }
         }
         Ok(())
     }
 
+    fn send_login_error(&mut self, error: twitter::LoginError) {
+        log::warn!(target: TARGET, "Login failed: {:?}", error);
+        self.send_to_ui(ToUI::LoginError { error });
+    }
+
     fn send_to_ui(&mut self, msg: ToUI) {
         if let Err(e) = self.sender.send_event(msg) {
             log::warn!(target: TARGET, "Could not send message to ui: {:?}", e);
@@ -146,14 +648,141 @@ impl Runner {
         match msg {
             ToBackground::OpenTwitterLogin => self.open_twitter_login().await,
             ToBackground::TwitterPin { pin } => self.login(pin).await,
-            ToBackground::LoadInitialTweets => self.load_tweets(|t| t.start()).await,
-            ToBackground::LoadOlderTweets => self.load_tweets(|t| t.older(None)).await,
-            ToBackground::LoadNewerTweets => self.load_tweets(|t| t.newer(None)).await,
-            ToBackground::LoadImage { key, context } => self.load_image(key, context),
-            ToBackground::SetLatestTweet { id } => {
-                self.config.twitter.latest = Some(id);
+            ToBackground::OAuthCallback { callback } => self.handle_callback(callback).await,
+            ToBackground::CancelLogin => self.cancel_login(),
+            ToBackground::UnlockCredentials { passphrase } => {
+                self.unlock_credentials(passphrase).await
+            }
+            ToBackground::ForgetCredentials => self.forget_credentials(),
+            ToBackground::SetConsumer { key, secret } => {
+                self.config.consumer = Some(ConsumerConfig { key, secret });
+                self.config.save();
+                if self.sessions.is_empty() {
+                    self.login_from_config().await;
+                }
+            }
+            ToBackground::BrowseAsGuest => self.browse_as_guest().await,
+            ToBackground::LoadInitialTweets { timeline } => {
+                self.load_tweets(timeline, Direction::Initial).await
+            }
+            ToBackground::LoadOlderTweets { timeline } => {
+                self.load_tweets(timeline, Direction::Older).await
+            }
+            ToBackground::LoadNewerTweets { timeline } => {
+                self.load_tweets(timeline, Direction::Newer).await
+            }
+            ToBackground::LoadGap { timeline, gap } => {
+                self.load_tweets(timeline, Direction::Gap(gap)).await
+            }
+            ToBackground::Search { query } => {
+                self.load_tweets(TimelineKind::Search(query), Direction::Initial)
+                    .await
+            }
+            ToBackground::SaveSearch { query } => {
+                if !self.config.saved_searches.contains(&query) {
+                    self.config.saved_searches.push(query);
+                    self.config.save();
+                }
+                self.send_saved_searches();
+            }
+            ToBackground::RemoveSavedSearch { query } => {
+                self.config.saved_searches.retain(|q| *q != query);
                 self.config.save();
+                self.send_saved_searches();
+            }
+            ToBackground::PostTweet { draft, tweet } => self.post_tweet(draft, tweet),
+            ToBackground::PostThread { draft, tweets } => self.post_thread(draft, tweets),
+            ToBackground::TweetSent { user, post, result } => self.tweet_sent(user, post, *result),
+            ToBackground::TweetAction { id, action } => self.tweet_action(id, action).await,
+            ToBackground::LoadConversation { tweet } => self.load_conversation(*tweet),
+            ToBackground::LoadLists => self.load_lists().await,
+            ToBackground::LoadProfile { screen_name } => self.load_profile(screen_name).await,
+            ToBackground::LoadImage { key, context } => self.load_image(key, context),
+            ToBackground::LoadPreview { path } => self.load_preview(path),
+            ToBackground::SaveDraft { draft } => {
+                if let Some(name) = self.active_account() {
+                    self.drafts.store(&name, draft);
+                }
+            }
+            ToBackground::DeleteDraft { id } => {
+                if let Some(name) = self.active_account() {
+                    self.drafts.remove(&name, id);
+                }
+            }
+            ToBackground::ScheduleTweet { tweet } => {
+                if let Some(name) = self.active_account() {
+                    self.schedule.add(&name, tweet);
+                    self.send_scheduled_tweets();
+                }
+            }
+            ToBackground::CancelScheduledTweet { id } => {
+                if let Some(name) = self.active_account() {
+                    self.schedule.remove(&name, id);
+                    self.send_scheduled_tweets();
+                }
+            }
+            ToBackground::SetLatestTweet { timeline, id } => {
+                let name = match self.active_account() {
+                    Some(name) => name,
+                    None => return,
+                };
+                if let Some(account) = self.config.account_mut(&name) {
+                    if account.set_read_marker(&timeline, id) {
+                        self.config.save();
+                    }
+                }
+            }
+            ToBackground::SetColumns { columns } => {
+                let name = match self.session_mut() {
+                    Some(session) => {
+                        session.columns = columns.clone();
+                        if session.user.is_guest() {
+                            // The columns of guests aren't remembered
+                            return;
+                        }
+                        session.user.name.clone()
+                    }
+                    None => return,
+                };
+                if let Some(account) = self.config.account_mut(&name) {
+                    account.columns = columns;
+                    self.config.save();
+                }
+            }
+            ToBackground::SwitchAccount { name } => self.switch_account(name),
+            ToBackground::AddAccount => {
+                self.state = BackgroundState::NotLoggedIn;
+                let accounts = self.account_names();
+                self.send_to_ui(ToUI::LoggedOut {
+                    accounts,
+                    message: None,
+                });
             }
+            ToBackground::RateLimit {
+                user,
+                endpoint,
+                status,
+            } => self.task_rate_limit(user, endpoint, status),
+            ToBackground::RateLimited {
+                user,
+                endpoint,
+                reset,
+                request,
+            } => self.rate_limited(user, endpoint, reset, request.map(|r| *r)),
+            ToBackground::SessionExpired { user } => {
+                if self.session_mut().map(|s| s.user.id) == Some(user) {
+                    self.session_expired();
+                }
+            }
+            ToBackground::Logout => self.logout().await,
+            ToBackground::LeaveGuest => self.leave_guest(),
+        }
+    }
+
+    fn switch_account(&mut self, name: String) {
+        match self.find_session(&name) {
+            Some(idx) => self.activate_session(idx),
+            None => log::warn!(target: TARGET, "Unknown account {:?}", name),
         }
     }
 
@@ -166,116 +795,966 @@ impl Runner {
         });
     }
 
-    async fn open_twitter_login(&mut self) {
-        let request = twitter::AuthRequest::new().await;
-        open::that(request.url()).unwrap();
-        self.state = BackgroundState::Authing(request);
+    fn load_preview(&self, path: PathBuf) {
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let preview = preview::load(&path).await;
+            if let Err(e) = &preview {
+                log::warn!(target: TARGET, "Could not load a preview of {:?}: {}", path, e);
+            }
+            let _ = sender.send_event(ToUI::Preview { path, preview });
+        });
     }
 
-    async fn load_tweets<F>(&mut self, f: F)
-    where
-        F: FnOnce(egg_mode::tweet::Timeline) -> egg_mode::tweet::TimelineFuture,
-    {
-        let timeline = if let BackgroundState::LoggedIn(state) = &mut self.state {
-            state.timeline.take().unwrap()
-        } else {
-            log::warn!(target: TARGET, "Could not load tweets; not logged in");
-            return;
+    async fn open_twitter_login(&mut self) {
+        let consumer = match self.config.consumer() {
+            Some(consumer) => consumer,
+            None => {
+                self.send_to_ui(ToUI::ConsumerMissing);
+                return;
+            }
         };
-        let min_id = timeline.min_id;
-        let max_id = timeline.max_id;
-        let future = f(timeline.with_page_size(10));
-        match future.await {
-            Ok((mut new_timeline, tweets)) => {
-                new_timeline.min_id = new_timeline.min_id.min(min_id).or(min_id);
-                new_timeline.max_id = new_timeline.max_id.max(max_id).or(max_id);
-                log::info!(target: TARGET, "Loaded {} tweets", tweets.len());
-                log::info!(
-                    target: TARGET,
-                    "Min: {:?}, max: {:?}",
-                    new_timeline.min_id,
-                    new_timeline.max_id
-                );
-                if let BackgroundState::LoggedIn(state) = &mut self.state {
-                    state.timeline = Some(new_timeline);
-                    self.send_to_ui(ToUI::Tweets {
-                        tweets: tweets.response,
-                        latest: self.config.twitter.latest,
-                    });
-                } else {
-                    log::warn!(target: TARGET, "Loaded tweets but we're logged out now");
+        if let Some(listener) = self.callback_listener.take() {
+            listener.abort();
+        }
+
+        let port = self
+            .config
+            .callback_port
+            .unwrap_or(twitter::DEFAULT_CALLBACK_PORT);
+        let mut listener = match twitter::CallbackListener::bind(port).await {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                log::warn!(target: TARGET, "Could not start callback server: {:?}", e);
+                None
+            }
+        };
+        self.send_to_ui(ToUI::Loading);
+        let mut request = None;
+        if let Some(callback) = listener.as_ref().map(|l| l.url()) {
+            match twitter::AuthRequest::new(consumer.clone(), callback).await {
+                Ok(r) => request = Some(r),
+                Err(e) => {
+                    // Most likely the callback url is not registered for this app
+                    log::warn!(
+                        target: TARGET,
+                        "Callback was not accepted, falling back to PIN: {:?}",
+                        e
+                    );
+                    listener = None;
                 }
             }
-            Err(e) => {
-                log::warn!(target: TARGET, "Could not load tweets: {:?}", e);
-                self.send_to_ui(ToUI::Error {
-                    error: e.to_string(),
-                });
-                if let BackgroundState::LoggedIn(state) = &mut self.state {
-                    // reset timeline
-                    state.timeline = Some({
-                        let mut timeline = egg_mode::tweet::home_timeline(&state.user.token);
-                        timeline.min_id = min_id;
-                        timeline.max_id = max_id;
-                        timeline
-                    })
+        }
+        let request = match request {
+            Some(request) => request,
+            None => match twitter::AuthRequest::new(consumer, "oob").await {
+                Ok(request) => request,
+                Err(e) => {
+                    self.send_login_error(twitter::LoginError::RequestToken(e.to_string()));
+                    return;
                 }
+            },
+        };
+        let url = request.url();
+        let browser_opened = match open::that(&url) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!(target: TARGET, "Could not open browser: {:?}", e);
+                false
             }
+        };
+        self.state = BackgroundState::Authing(request.clone());
+        self.send_to_ui(ToUI::Authorizing {
+            url,
+            browser_opened,
+        });
+
+        if let Some(listener) = listener {
+            let to_self = self.to_self.clone();
+            // This task is aborted when the login is completed, cancelled or expired
+            self.callback_listener = Some(tokio::spawn(async move {
+                match listener.accept(request).await {
+                    Ok(callback) => {
+                        let _ = to_self.send(ToBackground::OAuthCallback { callback });
+                    }
+                    Err(e) => log::warn!(target: TARGET, "Callback server failed: {:?}", e),
+                }
+            }));
         }
     }
 
-    async fn login(&mut self, pin: String) {
-        let request = match &self.state {
-            BackgroundState::Authing(request) => request.clone(),
-            _ => {
-                log::warn!(target: TARGET, "Not in authing state, ignoring login");
-                return;
-            }
-        };
-        self.handle_login_result(request.authenticate(pin).await);
+    async fn handle_callback(&mut self, callback: twitter::Callback) {
+        match callback {
+            twitter::Callback::Authorized { token, verifier } => match &self.state {
+                BackgroundState::Authing(request) if request.matches(&token) => {
+                    self.login(verifier).await
+                }
+                _ => log::warn!(target: TARGET, "Ignoring callback for unknown request"),
+            },
+            twitter::Callback::Denied { token } => match &self.state {
+                BackgroundState::Authing(request) if request.matches(&token) => {
+                    self.cancel_login();
+                    self.send_login_error(twitter::LoginError::Denied);
+                }
+                _ => log::warn!(target: TARGET, "Ignoring callback for unknown request"),
+            },
+        }
     }
 
-    fn handle_login_result(&mut self, user: egg_mode::error::Result<twitter::User>) {
-        match user {
-            Ok(user) => {
-                self.config.twitter.set_token(&user.token);
-                self.config.save();
+    fn cancel_login(&mut self) {
+        if let Some(listener) = self.callback_listener.take() {
+            listener.abort();
+        }
+        if let BackgroundState::Authing(_) = self.state {
+            self.state = BackgroundState::NotLoggedIn;
+        }
+    }
 
-                let mut timeline = egg_mode::tweet::home_timeline(&user.token);
-                if let Some(last_tweet) = self.config.twitter.latest {
-                    timeline.min_id = Some(last_tweet);
-                }
+    /// Load newer tweets for all columns of the active session that are due to be polled.
+    async fn poll_timelines(&mut self) {
+        let now = Instant::now();
+        let due: Vec<_> = match self.session_mut() {
+            Some(session) => session
+                .columns
+                .iter()
+                .filter(|kind| {
+                    let next_poll = session.timelines.get(*kind).and_then(|t| t.next_poll);
+                    matches!(next_poll, Some(next_poll) if next_poll <= now)
+                })
+                .cloned()
+                .collect(),
+            None => return,
+        };
+        for kind in due {
+            self.load_tweets(kind, Direction::Poll).await;
+        }
+    }
 
-                self.state = BackgroundState::LoggedIn(Box::new(LoggedIn {
-                    user: user.clone(),
-                    timeline: Some(timeline),
-                }));
-                self.send_to_ui(ToUI::LoggedIn { user });
-            }
-            Err(e) => {
-                self.send_to_ui(ToUI::Error {
-                    error: e.to_string(),
+    /// Run the requests that were delayed because of rate limits, if their limit has reset.
+    async fn run_queued(&mut self) {
+        let ready: Vec<_> = match self.session_mut() {
+            Some(session) => {
+                let rate_limits = &session.rate_limits;
+                let (ready, waiting) = session.queued.drain(..).partition(|request| {
+                    rate_limits.all_available_at(&request.endpoints()).is_none()
                 });
+                session.queued = waiting;
+                ready
             }
+            None => return,
+        };
+        for request in ready {
+            self.make_request(request).await;
         }
     }
-}
 
-fn check_for_new_version(sender: EventLoopProxy<ToUI>) {
-    tokio::spawn(async move {
-        #[derive(serde::Deserialize)]
-        struct Release {
-            tag_name: String,
-            draft: bool,
-            prerelease: bool,
-            html_url: String,
+    async fn make_request(&mut self, request: Request) {
+        match request {
+            Request::Tweets(kind, direction) => self.load_tweets(kind, direction).await,
+            Request::Lists => self.load_lists().await,
+            Request::Profile(screen_name) => self.load_profile(screen_name).await,
+            Request::TweetAction(id, action) => self.tweet_action(id, action).await,
+            Request::Conversation(tweet) => self.load_conversation(*tweet),
+            Request::Tweet { draft, tweet } => self.post_tweet(draft, tweet),
+            Request::Thread { draft, tweets } => self.post_thread(draft, tweets),
         }
-        let response = reqwest::Client::builder()
-            .user_agent("https://github.com/victorkoenders/twitter_client/")
-            .build()
-            .unwrap()
-            .get("https://api.github.com/repos/victorkoenders/twitter_client/releases")
-            .send()
+    }
+
+    /// Queue the request if the rate limit of one of its endpoints is reached, it's made once the
+    /// limit resets. Returns the request if it can be made right away.
+    fn check_rate_limit(&mut self, request: Request) -> Option<Request> {
+        let session = self.session_mut()?;
+        let endpoints = request.endpoints();
+        if session.rate_limits.all_available_at(&endpoints).is_none() {
+            return Some(request);
+        }
+        log::info!(target: TARGET, "Rate limit of {:?} reached, delaying request", endpoints);
+        session.queue(request);
+        None
+    }
+
+    /// A request for the session of the user with the given id was refused because the rate limit
+    /// of `endpoint` was reached. `request` is made again once the limit resets.
+    fn rate_limited(
+        &mut self,
+        user: u64,
+        endpoint: &'static str,
+        reset: Option<i32>,
+        request: Option<Request>,
+    ) {
+        log::warn!(target: TARGET, "Rate limit of {} reached", endpoint);
+        if let Some(session) = self.sessions.iter_mut().find(|s| s.user.id == user) {
+            session.rate_limits.exhausted(endpoint, reset);
+            if let Some(request) = request {
+                session.queue(request);
+            }
+        }
+        if self.session_mut().map(|s| s.user.id) == Some(user) {
+            self.send_rate_limits();
+        }
+    }
+
+    async fn load_tweets(&mut self, kind: TimelineKind, direction: Direction) {
+        let poll_interval = self.config.polling.interval(&kind);
+        let endpoint = rate_limit::timeline_endpoint(&kind);
+        let (token, name, since_id, max_id) = if let Some(session) = self.session_mut() {
+            if kind.requires_user() && session.user.is_guest() {
+                log::warn!(target: TARGET, "Can't load {:?} without logging in", kind);
+                return;
+            }
+            if let Some(available_at) = session.rate_limits.available_at(endpoint) {
+                log::info!(target: TARGET, "Rate limit of {} reached, delaying {:?}", endpoint, kind);
+                if let Direction::Poll = direction {
+                    session.timelines.entry(kind).or_default().next_poll = Some(available_at);
+                } else {
+                    session.queue(Request::Tweets(kind, direction));
+                }
+                return;
+            }
+            let timeline = session.timelines.entry(kind.clone()).or_default();
+            if let Direction::Initial = direction {
+                *timeline = Timeline::default();
+            }
+            let (since_id, max_id) = timeline.bounds(direction);
+            // Polling resumes when this request is done
+            timeline.next_poll = None;
+            let user = &session.user;
+            (user.token.clone(), user.name.clone(), since_id, max_id)
+        } else {
+            log::warn!(target: TARGET, "Could not load tweets; not logged in");
+            return;
+        };
+        let page_size = self.config.page_size();
+        let result = timeline::load(&kind, &token, page_size, since_id, max_id).await;
+        let rate_limited = matches!(&result, Err(e) if twitter::is_rate_limited(e));
+        if let Some(session) = self.session_mut() {
+            if session.user.name == name {
+                match &result {
+                    Ok(response) => session
+                        .rate_limits
+                        .update(endpoint, response.rate_limit_status),
+                    Err(e) if rate_limited => session
+                        .rate_limits
+                        .exhausted(endpoint, twitter::rate_limit_reset(e)),
+                    Err(_) => {}
+                }
+                if let Some(timeline) = session.timelines.get_mut(&kind) {
+                    timeline.schedule_poll(poll_interval, rate_limited);
+                }
+                if rate_limited && direction != Direction::Poll {
+                    // Try again once the limit resets
+                    session.queue(Request::Tweets(kind.clone(), direction));
+                }
+            }
+        }
+        self.send_rate_limits();
+        match result {
+            Ok(tweets) => {
+                log::info!(target: TARGET, "Loaded {} tweets for {:?}", tweets.len(), kind);
+                let full = tweets.len() >= page_size as usize;
+                let (gaps, reached_end) = match self.session_mut() {
+                    Some(session) if session.user.name == name => {
+                        let timeline = session.timelines.entry(kind.clone()).or_default();
+                        timeline.update(direction, &tweets, full);
+                        (timeline.gaps(), timeline.reached_end)
+                    }
+                    _ => {
+                        log::warn!(target: TARGET, "Loaded tweets but we're logged out now");
+                        return;
+                    }
+                };
+                let latest = self
+                    .config
+                    .account(&name)
+                    .and_then(|a| a.read_marker(&kind));
+                self.send_to_ui(ToUI::Tweets {
+                    timeline: kind,
+                    tweets: tweets.response,
+                    latest,
+                    polled: matches!(direction, Direction::Poll),
+                    gaps,
+                    reached_end,
+                });
+            }
+            Err(e) if twitter::is_unauthorized(&e) => self.session_expired(),
+            Err(e) if matches!(direction, Direction::Poll) || rate_limited => {
+                // Don't bother the user with errors they didn't ask for, or requests that are
+                // retried automatically
+                log::warn!(target: TARGET, "Could not load {:?}: {:?}", kind, e);
+            }
+            Err(e) => {
+                log::warn!(target: TARGET, "Could not load tweets: {:?}", e);
+                self.send_to_ui(ToUI::Error {
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Post a tweet from the composer, or queue it until the rate limit resets
+    fn post_tweet(&mut self, draft: u64, tweet: NewTweet) {
+        let task = match self.posting_task(draft) {
+            Some(task) => task,
+            None => return,
+        };
+        if let Some(Request::Tweet { draft, tweet }) =
+            self.check_rate_limit(Request::Tweet { draft, tweet })
+        {
+            tokio::spawn(post_tweet(task, draft, tweet));
+        }
+    }
+
+    /// Post a thread from the composer, or queue it until the rate limit resets
+    fn post_thread(&mut self, draft: u64, tweets: Vec<NewTweet>) {
+        let task = match self.posting_task(draft) {
+            Some(task) => task,
+            None => return,
+        };
+        if let Some(Request::Thread { draft, tweets }) =
+            self.check_rate_limit(Request::Thread { draft, tweets })
+        {
+            tokio::spawn(post_thread(task, draft, tweets));
+        }
+    }
+
+    /// A task for posting the draft with the given id, which is only possible when logged in
+    fn posting_task(&mut self, draft: u64) -> Option<Task> {
+        match self.task() {
+            Some(task) if !task.user.is_guest() => Some(task),
+            _ => {
+                self.send_to_ui(ToUI::PostFailed {
+                    draft,
+                    error: String::from("Log in to post tweets"),
+                });
+                None
+            }
+        }
+    }
+
+    /// Post the scheduled tweet that is due first. Tweets that were due while the application was
+    /// closed are posted as soon as their account is logged in.
+    fn post_scheduled(&mut self) {
+        let now = rate_limit::unix_now();
+        let (account, tweet) = match self.schedule.next(|name| self.find_session(name).is_some()) {
+            Some((account, tweet)) if tweet.post_at <= now => (account.to_owned(), tweet.clone()),
+            _ => return,
+        };
+        let idx = match self.find_session(&account) {
+            Some(idx) => idx,
+            None => return,
+        };
+        let rate_limits = &self.sessions[idx].rate_limits;
+        if rate_limits.all_available_at(&POST_ENDPOINTS).is_some() {
+            // `next_scheduled` waits for the limit to reset
+            return;
+        }
+        log::info!(
+            target: TARGET,
+            "Posting tweet {} of {:?}, scheduled {} seconds ago",
+            tweet.id(),
+            account,
+            now - tweet.post_at
+        );
+        // Scheduled tweets are posted one at a time, so the same one isn't picked up again
+        self.posting_scheduled = true;
+        let task = self.session_task(idx);
+        tokio::spawn(async move {
+            let result = send_tweet(&task, &tweet.draft.tweet(), None).await;
+            let id = tweet.id();
+            task.tweet_sent(Post::Scheduled { account, id }, result);
+        });
+    }
+
+    /// A task posted a tweet for the user with the given id, or failed to
+    fn tweet_sent(
+        &mut self,
+        user: u64,
+        post: Post,
+        result: Result<egg_mode::Response<egg_mode::tweet::Tweet>, PostError>,
+    ) {
+        match &result {
+            Ok(posted) => {
+                log::info!(target: TARGET, "Posted tweet {}", posted.id);
+                self.task_rate_limit(user, "statuses/update", posted.rate_limit_status);
+            }
+            Err(e) => log::warn!(target: TARGET, "Could not post {:?}: {:?}", post, e),
+        }
+        let active = self.session_mut().map(|s| s.user.id) == Some(user);
+        match (post, result) {
+            (Post::Scheduled { account, id }, result) => {
+                self.posting_scheduled = false;
+                match result {
+                    Ok(posted) => {
+                        self.schedule.remove(&account, id);
+                        if active {
+                            self.send_to_ui(ToUI::ScheduledTweetPosted {
+                                tweet: Box::new(posted.response),
+                            });
+                        }
+                    }
+                    Err(e) => match e.rate_limited() {
+                        // Posted again once the limit resets, see `next_scheduled`
+                        Some((endpoint, reset)) => self.rate_limited(user, endpoint, reset, None),
+                        None => {
+                            self.schedule.set_error(&account, id, e.to_string());
+                            if active && e.is_unauthorized() {
+                                return self.session_expired();
+                            }
+                            if active {
+                                self.send_to_ui(ToUI::Error {
+                                    error: format!("Could not post a scheduled tweet: {}", e),
+                                });
+                            }
+                        }
+                    },
+                }
+                self.send_scheduled_tweets();
+            }
+            (Post::Tweet { account, draft }, Ok(posted)) => {
+                self.drafts.remove(&account, draft);
+                if active {
+                    self.send_to_ui(ToUI::TweetPosted {
+                        draft,
+                        tweet: Box::new(posted.response),
+                    });
+                }
+            }
+            (
+                Post::ThreadTweet {
+                    account,
+                    draft,
+                    last,
+                },
+                Ok(posted),
+            ) => {
+                if last {
+                    self.drafts.remove(&account, draft);
+                } else {
+                    self.drafts.add_posted(&account, draft, posted.id);
+                }
+                if active {
+                    self.send_to_ui(ToUI::ThreadTweetPosted {
+                        draft,
+                        tweet: Box::new(posted.response),
+                    });
+                    if last {
+                        self.send_to_ui(ToUI::ThreadPosted { draft });
+                    }
+                }
+            }
+            // The draft is kept, so the user can try again
+            (Post::Tweet { draft, .. } | Post::ThreadTweet { draft, .. }, Err(e)) => {
+                if active && e.is_unauthorized() {
+                    self.session_expired();
+                } else if active {
+                    self.send_to_ui(ToUI::PostFailed {
+                        draft,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Like or retweet a tweet. The UI already shows the result, so we only report back if it
+    /// failed.
+    async fn tweet_action(&mut self, id: u64, action: TweetAction) {
+        let (token, user) = match self.session_mut() {
+            Some(session) if !session.user.is_guest() => {
+                (session.user.token.clone(), session.user.id)
+            }
+            _ => {
+                self.send_to_ui(ToUI::TweetActionFailed {
+                    id,
+                    action,
+                    error: String::from("Log in to like or retweet tweets"),
+                });
+                return;
+            }
+        };
+        if self
+            .check_rate_limit(Request::TweetAction(id, action))
+            .is_none()
+        {
+            return;
+        }
+        match action.send(id, &token).await {
+            Ok(response) => self.update_rate_limit(action.endpoint(), response.rate_limit_status),
+            Err(e) if twitter::is_unauthorized(&e) => self.session_expired(),
+            Err(e) if twitter::is_rate_limited(&e) => {
+                let reset = twitter::rate_limit_reset(&e);
+                let request = Request::TweetAction(id, action);
+                self.rate_limited(user, action.endpoint(), reset, Some(request));
+            }
+            Err(e) => {
+                log::warn!(target: TARGET, "Could not {:?} {}: {:?}", action, id, e);
+                self.send_to_ui(ToUI::TweetActionFailed {
+                    id,
+                    action,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Load the conversation around a tweet in a separate task, as it can take many requests
+    fn load_conversation(&mut self, tweet: egg_mode::tweet::Tweet) {
+        let task = match self.task() {
+            Some(task) => task,
+            None => return,
+        };
+        if let Some(Request::Conversation(tweet)) =
+            self.check_rate_limit(Request::Conversation(Box::new(tweet)))
+        {
+            tokio::spawn(load_conversation(task, *tweet));
+        }
+    }
+
+    /// A handle for a task that makes requests for the active session
+    fn task(&mut self) -> Option<Task> {
+        match self.state {
+            BackgroundState::LoggedIn(idx) => Some(self.session_task(idx)),
+            _ => None,
+        }
+    }
+
+    /// A handle for a task that makes requests for the session at the given index
+    fn session_task(&self, idx: usize) -> Task {
+        Task {
+            user: self.sessions[idx].user.clone(),
+            to_self: self.to_self.clone(),
+            to_ui: self.sender.clone(),
+        }
+    }
+
+    /// A task made a request for the session of the user with the given id
+    fn task_rate_limit(&mut self, user: u64, endpoint: &'static str, status: egg_mode::RateLimit) {
+        if let Some(session) = self.sessions.iter_mut().find(|s| s.user.id == user) {
+            session.rate_limits.update(endpoint, status);
+        }
+        if self.session_mut().map(|s| s.user.id) == Some(user) {
+            self.send_rate_limits();
+        }
+    }
+
+    /// Load the lists the user owns or is subscribed to
+    async fn load_lists(&mut self) {
+        let (token, id) = match self.session_mut() {
+            Some(session) if !session.user.is_guest() => {
+                (session.user.token.clone(), session.user.id)
+            }
+            _ => return,
+        };
+        if self.check_rate_limit(Request::Lists).is_none() {
+            return;
+        }
+        // A single page holds up to 1000 lists, which should be plenty
+        let result = async {
+            let owned = egg_mode::list::ownerships(id, &token)
+                .with_page_size(1000)
+                .call()
+                .await
+                .map_err(|e| ("lists/ownerships", e))?;
+            let subscribed = egg_mode::list::subscriptions(id, &token)
+                .with_page_size(1000)
+                .call()
+                .await
+                .map_err(|e| ("lists/subscriptions", e))?;
+            Ok((owned, subscribed))
+        }
+        .await;
+        match result {
+            Ok((owned, subscribed)) => {
+                self.update_rate_limit("lists/ownerships", owned.rate_limit_status);
+                self.update_rate_limit("lists/subscriptions", subscribed.rate_limit_status);
+                self.send_to_ui(ToUI::Lists {
+                    owned: owned.response.lists,
+                    subscribed: subscribed.response.lists,
+                })
+            }
+            Err((_, e)) if twitter::is_unauthorized(&e) => self.session_expired(),
+            Err((endpoint, e)) if twitter::is_rate_limited(&e) => {
+                let reset = twitter::rate_limit_reset(&e);
+                self.rate_limited(id, endpoint, reset, Some(Request::Lists));
+            }
+            Err((_, e)) => {
+                log::warn!(target: TARGET, "Could not load lists: {:?}", e);
+                self.send_to_ui(ToUI::Error {
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    async fn load_profile(&mut self, screen_name: String) {
+        let screen_name = match self.check_rate_limit(Request::Profile(screen_name)) {
+            Some(Request::Profile(screen_name)) => screen_name,
+            _ => return,
+        };
+        let (token, user) = match self.session_mut() {
+            Some(session) => (session.user.token.clone(), session.user.id),
+            None => return,
+        };
+        match egg_mode::user::show(screen_name.clone(), &token).await {
+            Ok(user) => {
+                self.update_rate_limit("users/show", user.rate_limit_status);
+                self.send_to_ui(ToUI::Profile {
+                    user: Box::new(user.response),
+                })
+            }
+            Err(e) if twitter::is_unauthorized(&e) => self.session_expired(),
+            Err(e) if twitter::is_rate_limited(&e) => {
+                let reset = twitter::rate_limit_reset(&e);
+                let request = Request::Profile(screen_name);
+                self.rate_limited(user, "users/show", reset, Some(request));
+            }
+            Err(e) => {
+                log::warn!(target: TARGET, "Could not load profile: {:?}", e);
+                self.send_to_ui(ToUI::Error {
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    async fn browse_as_guest(&mut self) {
+        let consumer = match self.config.consumer() {
+            Some(consumer) => consumer,
+            None => {
+                self.send_to_ui(ToUI::ConsumerMissing);
+                return;
+            }
+        };
+        self.send_to_ui(ToUI::Loading);
+        match egg_mode::auth::bearer_token(&consumer).await {
+            Ok(token) => {
+                let idx = self.add_session(twitter::User::guest(token));
+                self.activate_session(idx);
+            }
+            Err(e) => {
+                log::warn!(target: TARGET, "Could not get a bearer token: {:?}", e);
+                self.send_to_ui(ToUI::Error {
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    async fn login(&mut self, pin: String) {
+        let request = match &self.state {
+            BackgroundState::Authing(request) => request.clone(),
+            _ => {
+                log::warn!(target: TARGET, "Not in authing state, ignoring login");
+                return;
+            }
+        };
+        if request.is_expired() {
+            self.cancel_login();
+            self.send_login_error(twitter::LoginError::TimedOut);
+            return;
+        }
+        if let Some(listener) = self.callback_listener.take() {
+            listener.abort();
+        }
+        self.send_to_ui(ToUI::Loading);
+        self.handle_login_result(request.authenticate(pin).await);
+    }
+
+    fn handle_login_result(&mut self, user: egg_mode::error::Result<twitter::User>) {
+        match user {
+            Ok(user) => {
+                self.config.account_or_insert(&user.name);
+                self.config.save();
+                match &mut self.credentials {
+                    Some(credentials) => {
+                        credentials
+                            .account_or_insert(&user.name)
+                            .set_token(&user.token);
+                        credentials.save();
+                    }
+                    None => log::warn!(target: TARGET, "Credentials are locked, not saving token"),
+                }
+
+                let idx = self.add_session(user);
+                self.activate_session(idx);
+            }
+            Err(e) => {
+                // Stay in the authing state, so the user can try their PIN again
+                self.send_login_error(twitter::LoginError::Authenticate(e.to_string()));
+            }
+        }
+    }
+}
+
+/// Sleep until the given deadline, or forever if there is none
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Makes requests for a session outside of the runner, so it isn't blocked while they are made.
+/// Rate limits and expired tokens are reported back to the runner.
+struct Task {
+    user: twitter::User,
+    to_self: Sender<ToBackground>,
+    to_ui: EventLoopProxy<ToUI>,
+}
+
+impl Task {
+    fn rate_limit(&self, endpoint: &'static str, status: egg_mode::RateLimit) {
+        let _ = self.to_self.send(ToBackground::RateLimit {
+            user: self.user.id,
+            endpoint,
+            status,
+        });
+    }
+
+    fn rate_limited(&self, endpoint: &'static str, reset: Option<i32>, request: Option<Request>) {
+        let _ = self.to_self.send(ToBackground::RateLimited {
+            user: self.user.id,
+            endpoint,
+            reset,
+            request: request.map(Box::new),
+        });
+    }
+
+    fn session_expired(&self) {
+        let _ = self
+            .to_self
+            .send(ToBackground::SessionExpired { user: self.user.id });
+    }
+
+    fn send_to_ui(&self, msg: ToUI) {
+        if let Err(e) = self.to_ui.send_event(msg) {
+            log::warn!(target: TARGET, "Could not send message to ui: {:?}", e);
+        }
+    }
+
+    fn tweet_sent(
+        &self,
+        post: Post,
+        result: Result<egg_mode::Response<egg_mode::tweet::Tweet>, PostError>,
+    ) {
+        let _ = self.to_self.send(ToBackground::TweetSent {
+            user: self.user.id,
+            post,
+            result: Box::new(result),
+        });
+    }
+}
+
+/// What a tweet that is posted by a task is for
+#[derive(Debug)]
+enum Post {
+    /// The tweet of the draft of `account` with the given id
+    Tweet { account: String, draft: u64 },
+    /// A tweet of the thread of a draft, `last` is set for the last tweet of the thread
+    ThreadTweet {
+        account: String,
+        draft: u64,
+        last: bool,
+    },
+    /// The scheduled tweet of `account` with the given id
+    Scheduled { account: String, id: u64 },
+}
+
+/// Post a tweet from the composer. Attachments can take a while to upload, so this is done
+/// in a separate task that reports back with `TweetSent`.
+async fn post_tweet(task: Task, draft: u64, tweet: NewTweet) {
+    let result = send_tweet(&task, &tweet, Some(draft)).await;
+    if let Some((endpoint, reset)) = result.as_ref().err().and_then(PostError::rate_limited) {
+        return task.rate_limited(endpoint, reset, Some(Request::Tweet { draft, tweet }));
+    }
+    let account = task.user.name.clone();
+    task.tweet_sent(Post::Tweet { account, draft }, result);
+}
+
+/// Post the tweets of a thread in order, each one replying to the one before it. The UI is
+/// told about every tweet that was posted, so it can resume the thread if one fails.
+async fn post_thread(task: Task, draft: u64, tweets: Vec<NewTweet>) {
+    let mut tweets = tweets.into_iter();
+    let mut previous = None;
+    while let Some(mut tweet) = tweets.next() {
+        if previous.is_some() {
+            tweet.in_reply_to = previous;
+        }
+        let result = send_tweet(&task, &tweet, Some(draft)).await;
+        if let Some((endpoint, reset)) = result.as_ref().err().and_then(PostError::rate_limited) {
+            // The rest of the thread is posted once the limit resets, still replying to the
+            // tweet that was posted last
+            let tweets = std::iter::once(tweet).chain(tweets).collect();
+            return task.rate_limited(endpoint, reset, Some(Request::Thread { draft, tweets }));
+        }
+        previous = result.as_ref().ok().map(|posted| posted.id);
+        let post = Post::ThreadTweet {
+            account: task.user.name.clone(),
+            draft,
+            last: tweets.len() == 0,
+        };
+        task.tweet_sent(post, result);
+        if previous.is_none() {
+            break;
+        }
+    }
+}
+
+/// Upload the attachments of a tweet and post it. The progress of uploads is only reported
+/// for tweets that are posted from the composer, with the id of their draft.
+async fn send_tweet(
+    task: &Task,
+    tweet: &NewTweet,
+    draft: Option<u64>,
+) -> Result<egg_mode::Response<egg_mode::tweet::Tweet>, PostError> {
+    let token = &task.user.token;
+    let mut request = tweet.draft();
+    for (index, attachment) in tweet.media.iter().enumerate() {
+        let (kind, data) = attachment.read().await.map_err(PostError::Attachment)?;
+        let progress = draft.map(|draft| (draft, index));
+        let id = upload_media(task, progress, kind, &data, &attachment.alt_text)
+            .await
+            .map_err(|error| PostError::Upload {
+                file_name: attachment.file_name(),
+                error,
+            })?;
+        request.add_media(id);
+    }
+    request.send(token).await.map_err(PostError::Twitter)
+}
+
+/// Upload an attachment and wait until Twitter has processed it. `progress` is the id of the draft
+/// and the position of the attachment in it, if the UI should be told about the progress.
+async fn upload_media(
+    task: &Task,
+    progress: Option<(u64, usize)>,
+    kind: MediaKind,
+    data: &[u8],
+    alt_text: &str,
+) -> egg_mode::error::Result<egg_mode::media::MediaId> {
+    use egg_mode::media::ProgressInfo;
+
+    let token = &task.user.token;
+    let report = |stage| {
+        if let Some((draft, index)) = progress {
+            task.send_to_ui(ToUI::UploadProgress {
+                draft,
+                index,
+                stage,
+            });
+        }
+    };
+    report(UploadStage::Uploading);
+    let mut handle = kind.upload(data, token).await?;
+    loop {
+        match handle.progress {
+            None | Some(ProgressInfo::Success) => break,
+            Some(ProgressInfo::Failed(e)) => return Err(e.into()),
+            Some(ProgressInfo::Pending(wait)) | Some(ProgressInfo::InProgress(wait)) => {
+                report(UploadStage::Processing);
+                tokio::time::sleep(Duration::from_secs(wait.max(1))).await;
+                handle = egg_mode::media::get_status(handle.id, token).await?;
+            }
+        }
+    }
+    let alt_text = alt_text.trim();
+    if !alt_text.is_empty() {
+        egg_mode::media::set_metadata(&handle.id, alt_text, token).await?;
+    }
+    log::info!(target: TARGET, "Uploaded {:?} {:?}", kind, handle.id);
+    report(UploadStage::Done);
+    Ok(handle.id)
+}
+
+/// Load the tweets that the given tweet replies to, up to the root of the conversation, and the
+/// replies to the tweet.
+async fn load_conversation(task: Task, tweet: egg_mode::tweet::Tweet) {
+    let token = &task.user.token;
+    let mut ancestors = Vec::new();
+    let mut parent = tweet.in_reply_to_status_id;
+    while let Some(id) = parent {
+        if ancestors.len() >= MAX_ANCESTORS {
+            break;
+        }
+        match egg_mode::tweet::show(id, token).await {
+            Ok(response) => {
+                task.rate_limit("statuses/show", response.rate_limit_status);
+                parent = response.in_reply_to_status_id;
+                ancestors.push(response.response);
+            }
+            Err(e) if twitter::is_unauthorized(&e) => return task.session_expired(),
+            Err(e) if twitter::is_rate_limited(&e) => {
+                let reset = twitter::rate_limit_reset(&e);
+                let request = Request::Conversation(Box::new(tweet));
+                return task.rate_limited("statuses/show", reset, Some(request));
+            }
+            Err(e) => {
+                // Deleted or protected tweets end the conversation early
+                log::warn!(target: TARGET, "Could not load tweet {}: {:?}", id, e);
+                break;
+            }
+        }
+    }
+    ancestors.reverse();
+
+    // There is no API for replies, so look for them among the recent tweets to the author
+    let screen_name = tweet.user.as_ref().map(|u| u.screen_name.clone());
+    let mut replies = Vec::new();
+    if let Some(screen_name) = screen_name {
+        let result = egg_mode::search::search(format!("to:{}", screen_name))
+            .result_type(egg_mode::search::ResultType::Recent)
+            .count(REPLY_SEARCH_SIZE)
+            .since_tweet(tweet.id)
+            .call(token)
+            .await;
+        match result {
+            Ok(response) => {
+                task.rate_limit("search/tweets", response.rate_limit_status);
+                replies = response
+                    .response
+                    .statuses
+                    .into_iter()
+                    .filter(|t| t.in_reply_to_status_id == Some(tweet.id))
+                    .collect();
+                // Oldest first, like the rest of the conversation
+                replies.sort_by_key(|t| t.id);
+            }
+            Err(e) if twitter::is_unauthorized(&e) => return task.session_expired(),
+            Err(e) if twitter::is_rate_limited(&e) => {
+                let reset = twitter::rate_limit_reset(&e);
+                let request = Request::Conversation(Box::new(tweet));
+                return task.rate_limited("search/tweets", reset, Some(request));
+            }
+            Err(e) => {
+                log::warn!(target: TARGET, "Could not load replies: {:?}", e);
+                task.send_to_ui(ToUI::Error {
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+    task.send_to_ui(ToUI::Conversation {
+        id: tweet.id,
+        ancestors,
+        replies,
+    });
+}
+
+fn check_for_new_version(sender: EventLoopProxy<ToUI>) {
+    tokio::spawn(async move {
+        #[derive(serde::Deserialize)]
+        struct Release {
+            tag_name: String,
+            draft: bool,
+            prerelease: bool,
+            html_url: String,
+        }
+        let response = reqwest::Client::builder()
+            .user_agent("https://github.com/victorkoenders/twitter_client/")
+            .build()
+            .unwrap()
+            .get("https://api.github.com/repos/victorkoenders/twitter_client/releases")
+            .send()
             .await;
         let response = match response {
             Ok(response) => response.json::<Vec<Release>>().await,
@@ -303,12 +1782,89 @@ fn check_for_new_version(sender: EventLoopProxy<ToUI>) {
 enum BackgroundState {
     NotLoggedIn,
     Authing(twitter::AuthRequest),
-    LoggedIn(Box<LoggedIn>),
+    /// Logged in with the session at the given index in `Runner::sessions`
+    LoggedIn(usize),
 }
 
 struct LoggedIn {
     user: twitter::User,
-    timeline: Option<egg_mode::tweet::Timeline>,
+    timelines: HashMap<TimelineKind, Timeline>,
+    /// The timelines that are shown in the UI, these are polled for new tweets
+    columns: Vec<TimelineKind>,
+    rate_limits: RateLimits,
+    /// Requests that are waiting for their rate limit to reset
+    queued: Vec<Request>,
+}
+
+impl LoggedIn {
+    fn queue(&mut self, request: Request) {
+        if !self.queued.iter().any(|queued| queued.same_as(&request)) {
+            self.queued.push(request);
+        }
+    }
+
+    /// When the first queued request can be made
+    fn next_queued(&self) -> Option<Instant> {
+        self.queued
+            .iter()
+            .map(|request| {
+                self.rate_limits
+                    .all_available_at(&request.endpoints())
+                    .unwrap_or_else(Instant::now)
+            })
+            .min()
+    }
+
+    fn next_poll(&self) -> Option<Instant> {
+        self.columns
+            .iter()
+            .filter_map(|kind| self.timelines.get(kind)?.next_poll)
+            .min()
+    }
+}
+
+/// A request that waits for the rate limits of its endpoints to reset
+#[derive(Debug)]
+enum Request {
+    Tweets(TimelineKind, Direction),
+    Lists,
+    Profile(String),
+    TweetAction(u64, TweetAction),
+    Conversation(Box<egg_mode::tweet::Tweet>),
+    Tweet {
+        draft: u64,
+        tweet: NewTweet,
+    },
+    /// The tweets of a thread that haven't been posted yet
+    Thread {
+        draft: u64,
+        tweets: Vec<NewTweet>,
+    },
+}
+
+impl Request {
+    fn endpoints(&self) -> Vec<&'static str> {
+        match self {
+            Self::Tweets(kind, _) => vec![rate_limit::timeline_endpoint(kind)],
+            Self::Lists => vec!["lists/ownerships", "lists/subscriptions"],
+            Self::Profile(_) => vec!["users/show"],
+            Self::TweetAction(_, action) => vec![action.endpoint()],
+            Self::Conversation(_) => vec!["statuses/show", "search/tweets"],
+            Self::Tweet { .. } | Self::Thread { .. } => POST_ENDPOINTS.to_vec(),
+        }
+    }
+
+    /// Whether making both requests would load the same thing twice. Posts and actions are
+    /// never the same, as each of them changes something.
+    fn same_as(&self, other: &Request) -> bool {
+        match (self, other) {
+            (Self::Tweets(a, x), Self::Tweets(b, y)) => a == b && x == y,
+            (Self::Lists, Self::Lists) => true,
+            (Self::Profile(a), Self::Profile(b)) => a == b,
+            (Self::Conversation(a), Self::Conversation(b)) => a.id == b.id,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -317,16 +1873,120 @@ enum ToBackground {
     TwitterPin {
         pin: String,
     },
-    LoadInitialTweets,
-    LoadOlderTweets,
-    LoadNewerTweets,
+    /// The browser was redirected to our callback server
+    OAuthCallback {
+        callback: twitter::Callback,
+    },
+    CancelLogin,
+    UnlockCredentials {
+        passphrase: String,
+    },
+    /// Delete the saved logins, as the user forgot their passphrase
+    ForgetCredentials,
+    SetConsumer {
+        key: String,
+        secret: String,
+    },
+    BrowseAsGuest,
+    LoadInitialTweets {
+        timeline: TimelineKind,
+    },
+    LoadOlderTweets {
+        timeline: TimelineKind,
+    },
+    LoadNewerTweets {
+        timeline: TimelineKind,
+    },
+    LoadGap {
+        timeline: TimelineKind,
+        gap: Gap,
+    },
+    /// Start a new search, older results are loaded with `LoadOlderTweets`
+    Search {
+        query: String,
+    },
+    SaveSearch {
+        query: String,
+    },
+    RemoveSavedSearch {
+        query: String,
+    },
+    PostTweet {
+        draft: u64,
+        tweet: NewTweet,
+    },
+    PostThread {
+        draft: u64,
+        tweets: Vec<NewTweet>,
+    },
+    SaveDraft {
+        draft: Draft,
+    },
+    DeleteDraft {
+        id: u64,
+    },
+    ScheduleTweet {
+        tweet: ScheduledTweet,
+    },
+    CancelScheduledTweet {
+        id: u64,
+    },
+    TweetAction {
+        id: u64,
+        action: TweetAction,
+    },
+    LoadConversation {
+        tweet: Box<egg_mode::tweet::Tweet>,
+    },
+    /// A spawned task posted a tweet for the user with the given id, or failed to
+    TweetSent {
+        user: u64,
+        post: Post,
+        result: Box<Result<egg_mode::Response<egg_mode::tweet::Tweet>, PostError>>,
+    },
+    /// A spawned task made a request for the session of the user with the given id
+    RateLimit {
+        user: u64,
+        endpoint: &'static str,
+        status: egg_mode::RateLimit,
+    },
+    /// A spawned task was refused because the rate limit of `endpoint` was reached, `request` is
+    /// made again once the limit resets
+    RateLimited {
+        user: u64,
+        endpoint: &'static str,
+        reset: Option<i32>,
+        request: Option<Box<Request>>,
+    },
+    /// A spawned task found that the token of the user with the given id is no longer valid
+    SessionExpired {
+        user: u64,
+    },
+    /// Load the lists of the logged in user
+    LoadLists,
+    LoadProfile {
+        screen_name: String,
+    },
     LoadImage {
         key: image::Key,
         context: image::LoadContext,
     },
+    LoadPreview {
+        path: PathBuf,
+    },
     SetLatestTweet {
+        timeline: TimelineKind,
         id: u64,
     },
+    SetColumns {
+        columns: Vec<TimelineKind>,
+    },
+    SwitchAccount {
+        name: String,
+    },
+    AddAccount,
+    Logout,
+    LeaveGuest,
 }
 
 #[derive(Debug)]
@@ -334,15 +1994,114 @@ pub enum ToUI {
     Repaint,
     Disconnect,
     Loading,
+    /// There is no consumer key pair configured, so we can't log in
+    ConsumerMissing,
+    /// The user has to enter their passphrase before we can log in. If `exists` is false, the
+    /// passphrase will be used to create new credentials.
+    CredentialsLocked {
+        exists: bool,
+    },
+    CredentialsUnlocked,
+    /// The login page was opened, `browser_opened` is false if the user has to open `url` manually
+    Authorizing {
+        url: String,
+        browser_opened: bool,
+    },
+    LoginError {
+        error: twitter::LoginError,
+    },
     LoggedIn {
         user: twitter::User,
+        accounts: Vec<String>,
+        /// The timelines the user had open as columns
+        columns: Vec<TimelineKind>,
+        prefetch_threshold: usize,
+    },
+    LoggedOut {
+        accounts: Vec<String>,
+        message: Option<String>,
     },
     Error {
         error: String,
     },
     Tweets {
+        timeline: TimelineKind,
         tweets: Vec<egg_mode::tweet::Tweet>,
         latest: Option<u64>,
+        /// These were loaded automatically rather than requested by the user
+        polled: bool,
+        /// All tweets that may be missing from this timeline
+        gaps: Vec<Gap>,
+        /// There are no tweets older than the ones that were loaded
+        reached_end: bool,
+    },
+    SavedSearches {
+        queries: Vec<String>,
+    },
+    Lists {
+        owned: Vec<egg_mode::list::List>,
+        subscribed: Vec<egg_mode::list::List>,
+    },
+    Profile {
+        user: Box<egg_mode::user::TwitterUser>,
+    },
+    /// The tweet of the draft with the given id was posted, and the draft deleted
+    TweetPosted {
+        draft: u64,
+        tweet: Box<egg_mode::tweet::Tweet>,
+    },
+    /// Posting the draft with the given id failed, the draft is kept
+    PostFailed {
+        draft: u64,
+        error: String,
+    },
+    /// One tweet of the thread of the draft with the given id was posted
+    ThreadTweetPosted {
+        draft: u64,
+        tweet: Box<egg_mode::tweet::Tweet>,
+    },
+    /// All tweets of the thread of the draft with the given id were posted, and the draft deleted
+    ThreadPosted {
+        draft: u64,
+    },
+    /// The unsent tweets of the logged in user
+    Drafts {
+        drafts: Vec<Draft>,
+    },
+    /// The tweets the logged in user scheduled, the first to be posted first
+    ScheduledTweets {
+        tweets: Vec<ScheduledTweet>,
+    },
+    /// A scheduled tweet of the logged in user was posted
+    ScheduledTweetPosted {
+        tweet: Box<egg_mode::tweet::Tweet>,
+    },
+    /// The attachment at `index` of the draft that is being posted reached `stage`
+    UploadProgress {
+        draft: u64,
+        index: usize,
+        stage: UploadStage,
+    },
+    /// The preview of an attached image, or why it couldn't be shown
+    Preview {
+        path: PathBuf,
+        preview: Result<Preview, String>,
+    },
+    /// Liking or retweeting a tweet failed, so the UI should undo `action`
+    TweetActionFailed {
+        id: u64,
+        action: TweetAction,
+        error: String,
+    },
+    /// The conversation around the tweet with the given id
+    Conversation {
+        id: u64,
+        ancestors: Vec<egg_mode::tweet::Tweet>,
+        replies: Vec<egg_mode::tweet::Tweet>,
+    },
+    /// The rate limit status of every endpoint the active session has called
+    RateLimits {
+        limits: Vec<(&'static str, egg_mode::RateLimit)>,
     },
     ImageLoaded(image::ToUIImage),
     NewVersionAvailable {
@@ -0,0 +1,281 @@
+use egg_mode::{
+    error::Result,
+    media::{self, media_types, MediaHandle},
+    tweet::{DraftTweet, Tweet},
+    Response, Token,
+};
+use std::path::{Path, PathBuf};
+
+/// The most images that can be attached to a tweet, GIFs and videos have to be attached alone
+pub const MAX_IMAGES: usize = 4;
+
+/// A tweet that the user wants to post
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct NewTweet {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub in_reply_to: Option<u64>,
+    /// Users in the conversation that shouldn't be mentioned in the reply
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub exclude_reply_user_ids: Vec<u64>,
+    /// The url of the tweet that is quoted
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub quote_url: Option<String>,
+    /// Files that are uploaded right before the tweet is posted
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub media: Vec<Attachment>,
+}
+
+impl NewTweet {
+    pub fn draft(&self) -> DraftTweet {
+        let mut draft = DraftTweet::new(self.text.clone());
+        if let Some(id) = self.in_reply_to {
+            // Twitter adds the @mentions of the conversation, these don't count toward the length
+            draft = draft
+                .in_reply_to(id)
+                .auto_populate_reply_metadata(true)
+                .exclude_reply_user_ids(self.exclude_reply_user_ids.clone());
+        }
+        if let Some(url) = &self.quote_url {
+            draft = draft.attachment_url(url.clone());
+        }
+        draft
+    }
+}
+
+/// Why a tweet couldn't be posted
+#[derive(Debug)]
+pub enum PostError {
+    /// An attachment couldn't be read, or Twitter doesn't accept it
+    Attachment(String),
+    Upload {
+        file_name: String,
+        error: egg_mode::error::Error,
+    },
+    Twitter(egg_mode::error::Error),
+}
+
+impl PostError {
+    pub fn is_unauthorized(&self) -> bool {
+        match self {
+            Self::Attachment(_) => false,
+            Self::Upload { error, .. } | Self::Twitter(error) => {
+                super::twitter::is_unauthorized(error)
+            }
+        }
+    }
+
+    /// The endpoint whose rate limit was reached and when it resets, if that's why posting failed
+    pub fn rate_limited(&self) -> Option<(&'static str, Option<i32>)> {
+        let (endpoint, error) = match self {
+            Self::Attachment(_) => return None,
+            Self::Upload { error, .. } => ("media/upload", error),
+            Self::Twitter(error) => ("statuses/update", error),
+        };
+        super::twitter::is_rate_limited(error)
+            .then(|| (endpoint, super::twitter::rate_limit_reset(error)))
+    }
+}
+
+impl std::fmt::Display for PostError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Attachment(error) => write!(f, "{}", error),
+            Self::Upload { file_name, error } => {
+                write!(f, "Could not upload {}: {}", file_name, error)
+            }
+            Self::Twitter(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// A file that is attached to a tweet
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Attachment {
+    pub path: PathBuf,
+    /// A description of the image for people who can't see it
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub alt_text: String,
+}
+
+impl Attachment {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            alt_text: String::new(),
+        }
+    }
+
+    pub fn kind(&self) -> Option<MediaKind> {
+        MediaKind::from_path(&self.path)
+    }
+
+    pub fn file_name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || self.path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Read the file, checking that Twitter will accept it
+    pub async fn read(&self) -> std::result::Result<(MediaKind, Vec<u8>), String> {
+        let kind = self
+            .kind()
+            .ok_or_else(|| format!("{} is not an image, GIF or video", self.file_name()))?;
+        let error = |e: std::io::Error| format!("Could not read {}: {}", self.file_name(), e);
+        // The file may have changed since it was attached, don't read it if it's too large now
+        let size = tokio::fs::metadata(&self.path).await.map_err(error)?.len();
+        if size > kind.max_size() {
+            return Err(format!(
+                "{} is too large, the limit is {} MB",
+                self.file_name(),
+                kind.max_size() / 1024 / 1024
+            ));
+        }
+        let data = tokio::fs::read(&self.path).await.map_err(error)?;
+        Ok((kind, data))
+    }
+}
+
+/// The types of media Twitter accepts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaKind {
+    Png,
+    Jpeg,
+    Webp,
+    Gif,
+    Mp4,
+}
+
+impl MediaKind {
+    /// The file extensions of all supported media
+    pub const EXTENSIONS: &'static [&'static str] = &["png", "jpg", "jpeg", "webp", "gif", "mp4"];
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        Some(match extension.as_str() {
+            "png" => Self::Png,
+            "jpg" | "jpeg" => Self::Jpeg,
+            "webp" => Self::Webp,
+            "gif" => Self::Gif,
+            "mp4" => Self::Mp4,
+            _ => return None,
+        })
+    }
+
+    /// Still images, up to `MAX_IMAGES` of these can be attached to a tweet
+    pub fn is_image(self) -> bool {
+        matches!(self, Self::Png | Self::Jpeg | Self::Webp)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png | Self::Jpeg | Self::Webp => "Image",
+            Self::Gif => "GIF",
+            Self::Mp4 => "Video",
+        }
+    }
+
+    /// The largest file of this kind that Twitter accepts, in bytes
+    pub fn max_size(self) -> u64 {
+        const MB: u64 = 1024 * 1024;
+        match self {
+            Self::Png | Self::Jpeg | Self::Webp => 5 * MB,
+            Self::Gif => 15 * MB,
+            Self::Mp4 => 512 * MB,
+        }
+    }
+
+    /// Upload a file of this kind. egg-mode sends it in chunks of 1 MB, GIFs and videos still
+    /// have to be processed by Twitter before they can be attached.
+    pub async fn upload(self, data: &[u8], token: &Token) -> Result<MediaHandle> {
+        let media_type = match self {
+            Self::Png => media_types::image_png(),
+            Self::Jpeg => media_types::image_jpg(),
+            Self::Webp => media_types::image_webp(),
+            Self::Gif => media_types::image_gif(),
+            Self::Mp4 => media_types::video_mp4(),
+        };
+        media::upload_media(data, &media_type, token).await
+    }
+}
+
+/// How far the upload of a single attachment has come
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadStage {
+    Uploading,
+    /// Twitter is processing the GIF or video
+    Processing,
+    Done,
+}
+
+/// Something the user can do with an existing tweet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TweetAction {
+    Like,
+    Unlike,
+    Retweet,
+    Unretweet,
+}
+
+impl TweetAction {
+    /// The action that reverts this one
+    pub fn undo(self) -> Self {
+        match self {
+            Self::Like => Self::Unlike,
+            Self::Unlike => Self::Like,
+            Self::Retweet => Self::Unretweet,
+            Self::Unretweet => Self::Retweet,
+        }
+    }
+
+    /// Update the flags and counts of the tweet to what they will be after this action.
+    pub fn apply(self, tweet: &mut Tweet) {
+        match self {
+            Self::Like => {
+                tweet.favorited = Some(true);
+                tweet.favorite_count += 1;
+            }
+            Self::Unlike => {
+                tweet.favorited = Some(false);
+                tweet.favorite_count = (tweet.favorite_count - 1).max(0);
+            }
+            Self::Retweet => {
+                tweet.retweeted = Some(true);
+                tweet.retweet_count += 1;
+            }
+            Self::Unretweet => {
+                tweet.retweeted = Some(false);
+                tweet.retweet_count = (tweet.retweet_count - 1).max(0);
+            }
+        }
+    }
+
+    pub async fn send(self, id: u64, token: &Token) -> Result<Response<Tweet>> {
+        match self {
+            Self::Like => egg_mode::tweet::like(id, token).await,
+            Self::Unlike => egg_mode::tweet::unlike(id, token).await,
+            Self::Retweet => egg_mode::tweet::retweet(id, token).await,
+            Self::Unretweet => egg_mode::tweet::unretweet(id, token).await,
+        }
+    }
+
+    /// What this action does, to use in messages like "Could not {} the tweet"
+    pub fn verb(self) -> &'static str {
+        match self {
+            Self::Like => "like",
+            Self::Unlike => "unlike",
+            Self::Retweet => "retweet",
+            Self::Unretweet => "undo the retweet of",
+        }
+    }
+
+    pub fn endpoint(self) -> &'static str {
+        match self {
+            Self::Like => "favorites/create",
+            Self::Unlike => "favorites/destroy",
+            Self::Retweet => "statuses/retweet",
+            Self::Unretweet => "statuses/unretweet",
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use egui::{epaint::Mesh, pos2, vec2, Color32, Rect};
+use std::path::Path;
+
+/// The longest side of a preview, in pixels
+const SIZE: u32 = 64;
+
+/// A small version of an image that is attached to a tweet
+#[derive(Clone)]
+pub struct Preview {
+    pub width: usize,
+    pub height: usize,
+    /// One square per pixel, a pixel wide, built once so drawing only has to place it
+    pub mesh: Mesh,
+}
+
+impl std::fmt::Debug for Preview {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("Preview")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Decode the image at the given path into a preview, GIFs show their first frame
+pub async fn load(path: &Path) -> Result<Preview, String> {
+    let data = tokio::fs::read(path).await.map_err(|e| e.to_string())?;
+    // Decoding a large image takes a while, so don't block the runner
+    let decode = move || {
+        let image = image::load_from_memory(&data).map_err(|e| e.to_string())?;
+        let thumbnail = image.thumbnail(SIZE, SIZE).to_rgba8();
+        let (width, height) = thumbnail.dimensions();
+        let mut mesh = Mesh::default();
+        for (x, y, p) in thumbnail.enumerate_pixels() {
+            let color = Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3]);
+            let square = Rect::from_min_size(pos2(x as f32, y as f32), vec2(1.0, 1.0));
+            mesh.add_colored_rect(square, color);
+        }
+        Ok(Preview {
+            width: width as usize,
+            height: height as usize,
+            mesh,
+        })
+    };
+    tokio::task::spawn_blocking(decode)
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,136 @@
+use super::TimelineKind;
+use egg_mode::RateLimit;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// The length of a rate limit window, used when Twitter doesn't tell us when it resets
+const WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// The endpoint that is used to load the given timeline.
+pub fn timeline_endpoint(kind: &TimelineKind) -> &'static str {
+    match kind {
+        TimelineKind::Home => "statuses/home_timeline",
+        TimelineKind::Mentions => "statuses/mentions_timeline",
+        TimelineKind::User(_) => "statuses/user_timeline",
+        TimelineKind::Likes(_) => "favorites/list",
+        TimelineKind::Search(_) => "search/tweets",
+        TimelineKind::List(_) => "lists/statuses",
+    }
+}
+
+/// The endpoints that posting a tweet uses, media is only uploaded for tweets with attachments
+pub const POST_ENDPOINTS: [&str; 2] = ["statuses/update", "media/upload"];
+
+/// The remaining budget of every endpoint we've called, as reported by Twitter. Limits are per
+/// token, so every session keeps its own.
+#[derive(Default)]
+pub struct RateLimits {
+    limits: BTreeMap<&'static str, RateLimit>,
+}
+
+impl RateLimits {
+    pub fn update(&mut self, endpoint: &'static str, status: RateLimit) {
+        self.limits.insert(endpoint, status);
+    }
+
+    /// Remember that we hit the limit of this endpoint, `reset` is the unix timestamp at which
+    /// requests are allowed again.
+    pub fn exhausted(&mut self, endpoint: &'static str, reset: Option<i32>) {
+        let reset = reset.unwrap_or_else(|| (unix_now() + WINDOW.as_secs()) as i32);
+        let limit = self.limits.get(endpoint).map_or(0, |l| l.limit);
+        self.limits.insert(
+            endpoint,
+            RateLimit {
+                limit,
+                remaining: 0,
+                reset,
+            },
+        );
+    }
+
+    /// When the next request to this endpoint can be made, or `None` if it can be made right away.
+    pub fn available_at(&self, endpoint: &str) -> Option<Instant> {
+        let limit = self.limits.get(endpoint)?;
+        if limit.remaining > 0 {
+            return None;
+        }
+        let wait = (limit.reset as i64 - unix_now() as i64).max(0) as u64;
+        if wait == 0 {
+            return None;
+        }
+        // Give Twitter a second to actually reset the window
+        Some(Instant::now() + Duration::from_secs(wait + 1))
+    }
+
+    /// When a request that uses all of the given endpoints can be made, or `None` if it can be
+    /// made right away.
+    pub fn all_available_at(&self, endpoints: &[&str]) -> Option<Instant> {
+        endpoints.iter().filter_map(|e| self.available_at(e)).max()
+    }
+
+    /// Whether we have to wait for the limit of any endpoint to reset
+    pub fn any_exhausted(&self) -> bool {
+        self.limits.keys().any(|e| self.available_at(e).is_some())
+    }
+
+    pub fn all(&self) -> Vec<(&'static str, RateLimit)> {
+        self.limits.iter().map(|(e, l)| (*e, *l)).collect()
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn available_until_exhausted() {
+        let mut limits = RateLimits::default();
+        assert_eq!(limits.available_at("statuses/update"), None);
+        let reset = (unix_now() + 60) as i32;
+        limits.update(
+            "statuses/update",
+            RateLimit {
+                limit: 300,
+                remaining: 1,
+                reset,
+            },
+        );
+        assert_eq!(limits.available_at("statuses/update"), None);
+        assert!(!limits.any_exhausted());
+
+        limits.exhausted("statuses/update", Some(reset));
+        let available_at = limits.available_at("statuses/update").unwrap();
+        assert!(available_at > Instant::now() + Duration::from_secs(59));
+        assert!(available_at <= Instant::now() + Duration::from_secs(61));
+        assert!(limits.any_exhausted());
+        // The limit of the endpoint is remembered
+        assert_eq!(limits.all()[0].1.limit, 300);
+        assert!(limits.all_available_at(&POST_ENDPOINTS).unwrap() >= available_at);
+        assert_eq!(limits.all_available_at(&["media/upload"]), None);
+    }
+
+    #[test]
+    fn available_once_reset() {
+        let mut limits = RateLimits::default();
+        limits.exhausted("search/tweets", Some(unix_now() as i32 - 1));
+        assert_eq!(limits.available_at("search/tweets"), None);
+        assert!(!limits.any_exhausted());
+    }
+
+    #[test]
+    fn exhausted_without_reset_waits_a_window() {
+        let mut limits = RateLimits::default();
+        limits.exhausted("users/show", None);
+        let wait = limits.available_at("users/show").unwrap() - Instant::now();
+        assert!(wait > WINDOW - Duration::from_secs(1));
+        assert!(wait <= WINDOW + Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,180 @@
+use super::{config::Error, drafts::Draft};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{Read, Write},
+};
+
+const TARGET: &str = "schedule";
+
+/// Stored next to `config.toml`
+const FILE_NAME: &str = "scheduled.toml";
+
+/// A tweet that is posted once `post_at` has passed
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ScheduledTweet {
+    /// When the tweet should be posted, as a unix timestamp
+    pub post_at: u64,
+    /// Posting the tweet failed, it isn't retried until the user schedules it again
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+    pub draft: Draft,
+}
+
+impl ScheduledTweet {
+    pub fn id(&self) -> u64 {
+        self.draft.id
+    }
+}
+
+/// The scheduled tweets of every account, by the name of the account. Tweets are only posted while
+/// their account is logged in, any that are overdue are posted as soon as it is.
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+pub struct Schedule {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    accounts: BTreeMap<String, Vec<ScheduledTweet>>,
+}
+
+impl Schedule {
+    pub fn load() -> Self {
+        match Self::try_load() {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                log::info!(target: TARGET, "No scheduled tweets loaded ({:?})", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn try_load() -> Result<Self, Error> {
+        let mut file = File::open(FILE_NAME)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        toml::from_str(&content).map_err(Into::into)
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.try_save() {
+            log::error!(target: TARGET, "Could not save scheduled tweets: {:?}", e);
+        }
+    }
+
+    fn try_save(&self) -> std::io::Result<()> {
+        let str = toml::to_string_pretty(&self).expect("Could not serialize scheduled tweets");
+        // Write to a temporary file first so a crash can't leave us with a half-written file
+        let tmp = format!("{}.tmp", FILE_NAME);
+        File::create(&tmp)?.write_all(str.as_bytes())?;
+        fs::rename(&tmp, FILE_NAME)
+    }
+
+    /// The scheduled tweets of an account, the first to be posted first
+    pub fn get(&self, account: &str) -> Vec<ScheduledTweet> {
+        let mut tweets = self.accounts.get(account).cloned().unwrap_or_default();
+        tweets.sort_by_key(|t| t.post_at);
+        tweets
+    }
+
+    /// Add the tweet, or replace the one with the same id
+    pub fn add(&mut self, account: &str, tweet: ScheduledTweet) {
+        let tweets = self.accounts.entry(account.to_owned()).or_default();
+        tweets.retain(|t| t.id() != tweet.id());
+        tweets.push(tweet);
+        self.save();
+    }
+
+    pub fn remove(&mut self, account: &str, id: u64) -> Option<ScheduledTweet> {
+        let tweets = self.accounts.get_mut(account)?;
+        let idx = tweets.iter().position(|t| t.id() == id)?;
+        let tweet = tweets.remove(idx);
+        if tweets.is_empty() {
+            self.accounts.remove(account);
+        }
+        self.save();
+        Some(tweet)
+    }
+
+    pub fn set_error(&mut self, account: &str, id: u64, error: String) {
+        let tweet = self
+            .accounts
+            .get_mut(account)
+            .and_then(|tweets| tweets.iter_mut().find(|t| t.id() == id));
+        if let Some(tweet) = tweet {
+            tweet.error = Some(error);
+            self.save();
+        }
+    }
+
+    /// The tweet that should be posted first, out of the accounts for which `logged_in` is true.
+    /// Returns the name of the account and the tweet.
+    pub fn next(&self, logged_in: impl Fn(&str) -> bool) -> Option<(&str, &ScheduledTweet)> {
+        self.accounts
+            .iter()
+            .filter(|(account, _)| logged_in(account))
+            .flat_map(|(account, tweets)| tweets.iter().map(move |t| (account.as_str(), t)))
+            .filter(|(_, tweet)| tweet.error.is_none())
+            .min_by_key(|(_, tweet)| tweet.post_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(id: u64, post_at: u64, error: Option<&str>) -> ScheduledTweet {
+        ScheduledTweet {
+            post_at,
+            error: error.map(String::from),
+            draft: Draft {
+                id,
+                text: format!("Tweet {}", id),
+                ..Draft::new()
+            },
+        }
+    }
+
+    fn schedule(accounts: Vec<(&str, Vec<ScheduledTweet>)>) -> Schedule {
+        Schedule {
+            accounts: accounts
+                .into_iter()
+                .map(|(name, tweets)| (name.to_owned(), tweets))
+                .collect(),
+        }
+    }
+
+    fn next_id(schedule: &Schedule, logged_in: &[&str]) -> Option<(String, u64)> {
+        schedule
+            .next(|name| logged_in.contains(&name))
+            .map(|(account, tweet)| (account.to_owned(), tweet.id()))
+    }
+
+    #[test]
+    fn first_due_of_logged_in_accounts() {
+        let schedule = schedule(vec![
+            ("alice", vec![tweet(1, 300, None), tweet(2, 100, None)]),
+            ("bob", vec![tweet(3, 200, None), tweet(4, 50, None)]),
+        ]);
+        assert_eq!(
+            next_id(&schedule, &["alice", "bob"]),
+            Some(("bob".into(), 4))
+        );
+        assert_eq!(next_id(&schedule, &["alice"]), Some(("alice".into(), 2)));
+        assert_eq!(next_id(&schedule, &[]), None);
+    }
+
+    #[test]
+    fn failed_tweets_are_skipped() {
+        let schedule = schedule(vec![(
+            "alice",
+            vec![tweet(1, 100, Some("Duplicate")), tweet(2, 200, None)],
+        )]);
+        assert_eq!(next_id(&schedule, &["alice"]), Some(("alice".into(), 2)));
+
+        let schedule = self::schedule(vec![("alice", vec![tweet(1, 100, Some("Duplicate"))])]);
+        assert_eq!(next_id(&schedule, &["alice"]), None);
+    }
+
+    #[test]
+    fn empty_schedule() {
+        assert_eq!(next_id(&Schedule::default(), &["alice"]), None);
+    }
+}
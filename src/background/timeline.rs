@@ -0,0 +1,273 @@
+use egg_mode::{error::Result, list::ListID, search, tweet::Tweet, Response, Token};
+use std::time::{Duration, Instant};
+
+/// Polling slows down by a factor of two every time we're rate limited, up to this many times
+const MAX_BACKOFF: u32 = 5;
+
+/// The different timelines that tweets can be loaded from
+#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum TimelineKind {
+    Home,
+    /// Tweets that mention the logged in user
+    Mentions,
+    /// The tweets of the user with the given screen name
+    User(String),
+    /// The tweets liked by the user with the given screen name
+    Likes(String),
+    Search(String),
+    /// The tweets of the members of the list with the given id
+    List(u64),
+}
+
+impl TimelineKind {
+    /// Returns `true` if this timeline can't be loaded without logging in.
+    pub fn requires_user(&self) -> bool {
+        matches!(self, Self::Home | Self::Mentions)
+    }
+
+    /// The user this timeline belongs to, if it shows the tweets or likes of a single user.
+    pub fn screen_name(&self) -> Option<&str> {
+        match self {
+            Self::User(name) | Self::Likes(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Initial,
+    Older,
+    Newer,
+    /// Newer tweets that are loaded automatically
+    Poll,
+    /// Tweets that may be missing between two pages that were loaded
+    Gap(Gap),
+}
+
+/// A range of tweets that may be missing from a timeline, with the bounds to load them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gap {
+    /// The newest tweet below the gap
+    pub since_id: u64,
+    /// The oldest tweet above the gap, minus one
+    pub max_id: u64,
+}
+
+/// A range of tweets without anything missing in between
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    min_id: u64,
+    max_id: u64,
+}
+
+impl Segment {
+    fn of(tweets: &[Tweet]) -> Option<Self> {
+        Some(Self {
+            min_id: tweets.iter().map(|t| t.id).min()?,
+            max_id: tweets.iter().map(|t| t.id).max()?,
+        })
+    }
+}
+
+/// Keeps track of the tweets that have been loaded for a timeline.
+#[derive(Default)]
+pub struct Timeline {
+    /// The ranges of tweets that were loaded, newest first. Between every two segments there is
+    /// a gap.
+    segments: Vec<Segment>,
+    /// Loading older tweets returned nothing
+    pub reached_end: bool,
+    /// When to check for new tweets, `None` if polling is disabled or a request is in flight
+    pub next_poll: Option<Instant>,
+    /// How many times in a row we were rate limited while polling
+    backoff: u32,
+}
+
+impl Timeline {
+    /// The `since_id` and `max_id` to load the next page in the given direction.
+    pub fn bounds(&self, direction: Direction) -> (Option<u64>, Option<u64>) {
+        match direction {
+            Direction::Initial => (None, None),
+            Direction::Older => (None, self.segments.last().map(|s| s.min_id - 1)),
+            Direction::Newer | Direction::Poll => (self.segments.first().map(|s| s.max_id), None),
+            Direction::Gap(gap) => (Some(gap.since_id), Some(gap.max_id)),
+        }
+    }
+
+    pub fn gaps(&self) -> Vec<Gap> {
+        self.segments
+            .windows(2)
+            .map(|w| Gap {
+                since_id: w[1].max_id,
+                max_id: w[0].min_id - 1,
+            })
+            .collect()
+    }
+
+    /// Schedule the next poll after a request finished. The interval is doubled for every time in
+    /// a row that we were rate limited.
+    pub fn schedule_poll(&mut self, interval: Option<Duration>, rate_limited: bool) {
+        if rate_limited {
+            self.backoff = (self.backoff + 1).min(MAX_BACKOFF);
+        } else {
+            self.backoff = 0;
+        }
+        self.next_poll =
+            interval.map(|interval| Instant::now() + interval * 2u32.pow(self.backoff));
+    }
+
+    /// Add a page of tweets that was loaded in the given direction. If the page is `full`, there
+    /// may be more tweets between it and the tweets we already have.
+    pub fn update(&mut self, direction: Direction, tweets: &[Tweet], full: bool) {
+        self.add_page(direction, Segment::of(tweets), full);
+    }
+
+    /// Add the range of a page, `None` if it was empty
+    fn add_page(&mut self, direction: Direction, page: Option<Segment>, full: bool) {
+        match direction {
+            Direction::Initial => {
+                self.segments = page.into_iter().collect();
+                self.reached_end = page.is_none();
+            }
+            Direction::Older => match (self.segments.last_mut(), page) {
+                (Some(last), Some(page)) => last.min_id = last.min_id.min(page.min_id),
+                (None, Some(page)) => self.segments.push(page),
+                (_, None) => self.reached_end = true,
+            },
+            Direction::Newer | Direction::Poll => match (self.segments.first_mut(), page) {
+                (Some(first), Some(page)) if !full => first.max_id = first.max_id.max(page.max_id),
+                (_, Some(page)) => self.segments.insert(0, page),
+                (_, None) => {}
+            },
+            Direction::Gap(gap) => {
+                // The segment below the gap, pages are loaded from the top of the gap down
+                let below = match self.segments.iter().position(|s| s.max_id == gap.since_id) {
+                    Some(idx) if idx > 0 => idx,
+                    _ => return,
+                };
+                if !full {
+                    // Nothing is missing anymore
+                    let removed = self.segments.remove(below);
+                    self.segments[below - 1].min_id = removed.min_id;
+                } else if let Some(page) = page {
+                    let above = &mut self.segments[below - 1];
+                    above.min_id = above.min_id.min(page.min_id);
+                }
+            }
+        }
+    }
+}
+
+/// Load a single page of tweets newer than `since_id` and no newer than `max_id`.
+pub async fn load(
+    kind: &TimelineKind,
+    token: &Token,
+    page_size: u32,
+    since_id: Option<u64>,
+    max_id: Option<u64>,
+) -> Result<Response<Vec<Tweet>>> {
+    let timeline = match kind {
+        TimelineKind::Home => egg_mode::tweet::home_timeline(token),
+        TimelineKind::Mentions => egg_mode::tweet::mentions_timeline(token),
+        TimelineKind::User(name) => egg_mode::tweet::user_timeline(name.clone(), true, true, token),
+        TimelineKind::Likes(name) => egg_mode::tweet::liked_by(name.clone(), token),
+        TimelineKind::List(id) => egg_mode::list::statuses(ListID::from_id(*id), true, token),
+        TimelineKind::Search(query) => {
+            let mut search = search::search(query.clone())
+                .result_type(search::ResultType::Recent)
+                .count(page_size);
+            if let Some(since_id) = since_id {
+                search = search.since_tweet(since_id);
+            }
+            if let Some(max_id) = max_id {
+                search = search.max_tweet(max_id);
+            }
+            let result = search.call(token).await?;
+            return Ok(Response::map(result, |r| r.statuses));
+        }
+    };
+    timeline
+        .with_page_size(page_size as i32)
+        .call(since_id, max_id)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(min_id: u64, max_id: u64) -> Option<Segment> {
+        Some(Segment { min_id, max_id })
+    }
+
+    fn timeline(min_id: u64, max_id: u64) -> Timeline {
+        let mut timeline = Timeline::default();
+        timeline.add_page(Direction::Initial, page(min_id, max_id), true);
+        timeline
+    }
+
+    #[test]
+    fn overlapping_pages_are_merged() {
+        let mut timeline = timeline(100, 200);
+        timeline.add_page(Direction::Newer, page(190, 250), false);
+        timeline.add_page(Direction::Older, page(50, 99), true);
+        assert!(timeline.gaps().is_empty());
+        assert_eq!(timeline.bounds(Direction::Newer), (Some(250), None));
+        assert_eq!(timeline.bounds(Direction::Older), (None, Some(49)));
+        timeline.add_page(Direction::Older, None, false);
+        assert!(timeline.reached_end);
+    }
+
+    #[test]
+    fn full_newer_page_leaves_a_gap() {
+        let mut timeline = timeline(100, 200);
+        timeline.add_page(Direction::Poll, page(300, 400), true);
+        let gap = Gap {
+            since_id: 200,
+            max_id: 299,
+        };
+        assert_eq!(timeline.gaps(), vec![gap]);
+        assert_eq!(timeline.bounds(Direction::Gap(gap)), (Some(200), Some(299)));
+        assert_eq!(timeline.bounds(Direction::Newer), (Some(400), None));
+    }
+
+    #[test]
+    fn filling_a_gap() {
+        let mut timeline = timeline(100, 200);
+        timeline.add_page(Direction::Newer, page(300, 400), true);
+        let gap = timeline.gaps()[0];
+
+        // A full page only loads the top of the gap
+        timeline.add_page(Direction::Gap(gap), page(250, 299), true);
+        let gap = Gap {
+            since_id: 200,
+            max_id: 249,
+        };
+        assert_eq!(timeline.gaps(), vec![gap]);
+
+        timeline.add_page(Direction::Gap(gap), page(201, 249), false);
+        assert!(timeline.gaps().is_empty());
+        assert_eq!(timeline.bounds(Direction::Older), (None, Some(99)));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let interval = Duration::from_secs(60);
+        let mut timeline = Timeline::default();
+        for _ in 0..MAX_BACKOFF + 3 {
+            timeline.schedule_poll(Some(interval), true);
+        }
+        assert_eq!(timeline.backoff, MAX_BACKOFF);
+        let wait = timeline.next_poll.unwrap() - Instant::now();
+        let max_wait = interval * 2u32.pow(MAX_BACKOFF);
+        assert!(wait <= max_wait && wait > max_wait - Duration::from_secs(1));
+
+        timeline.schedule_poll(Some(interval), false);
+        assert_eq!(timeline.backoff, 0);
+        assert!(timeline.next_poll.unwrap() <= Instant::now() + interval);
+        timeline.schedule_poll(None, true);
+        assert_eq!(timeline.next_poll, None);
+    }
+}
@@ -0,0 +1,239 @@
+use super::AuthRequest;
+use std::io;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+const TARGET: &str = "OAuth callback";
+
+pub const DEFAULT_PORT: u16 = 41_293;
+
+const RESPONSE_AUTHORIZED: &str = "Logged in, you can close this window now.";
+const RESPONSE_DENIED: &str = "Login cancelled, you can close this window now.";
+
+/// A short-lived HTTP listener on 127.0.0.1 that twitter redirects the browser to after the user
+/// authorized the application.
+pub struct CallbackListener {
+    listener: TcpListener,
+    port: u16,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Callback {
+    Authorized {
+        token: String,
+        verifier: String,
+    },
+    /// The user cancelled the login request with the given oauth token
+    Denied {
+        token: String,
+    },
+}
+
+impl Callback {
+    /// The oauth token of the login request this callback is for
+    pub fn token(&self) -> &str {
+        match self {
+            Self::Authorized { token, .. } | Self::Denied { token } => token,
+        }
+    }
+}
+
+impl CallbackListener {
+    pub async fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        let port = listener.local_addr()?.port();
+        Ok(Self { listener, port })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}/callback", self.port)
+    }
+
+    /// Wait for the browser to be redirected to our callback url for the given login request.
+    /// Requests to other paths (e.g. `/favicon.ico`) or for other login requests are ignored.
+    pub async fn accept(self, request: AuthRequest) -> io::Result<Callback> {
+        loop {
+            let (mut stream, addr) = self.listener.accept().await?;
+            log::info!(target: TARGET, "Connection from {}", addr);
+
+            let mut buffer = Vec::new();
+            let mut chunk = [0u8; 1024];
+            let mut failed = false;
+            while !buffer.windows(4).any(|w| w == b"\r\n\r\n") && buffer.len() < 16 * 1024 {
+                match stream.read(&mut chunk).await {
+                    Ok(0) => break,
+                    Ok(len) => buffer.extend_from_slice(&chunk[..len]),
+                    Err(e) => {
+                        // Only this connection is broken, the browser may still come by
+                        log::warn!(target: TARGET, "Could not read request: {:?}", e);
+                        failed = true;
+                        break;
+                    }
+                }
+            }
+            if failed {
+                continue;
+            }
+            let lines = String::from_utf8_lossy(&buffer);
+            let callback = lines
+                .lines()
+                .next()
+                .and_then(|line| parse_callback(line, &request));
+
+            let body = match &callback {
+                Some(Callback::Authorized { .. }) => RESPONSE_AUTHORIZED,
+                Some(Callback::Denied { .. }) => RESPONSE_DENIED,
+                None => "Not found",
+            };
+            let status = if callback.is_some() {
+                "200 OK"
+            } else {
+                "404 Not Found"
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                log::warn!(target: TARGET, "Could not write response: {:?}", e);
+            }
+
+            if let Some(callback) = callback {
+                return Ok(callback);
+            }
+        }
+    }
+}
+
+/// The callback of a request line, if it is for the given login request
+fn parse_callback(line: &str, request: &AuthRequest) -> Option<Callback> {
+    parse_request_line(line).filter(|callback| request.matches(callback.token()))
+}
+
+/// Parse a request line like `GET /callback?oauth_token=...&oauth_verifier=... HTTP/1.1`
+fn parse_request_line(line: &str) -> Option<Callback> {
+    let mut parts = line.split(' ');
+    if parts.next()? != "GET" {
+        return None;
+    }
+    let (path, query) = parts.next()?.split_once('?')?;
+    if path != "/callback" {
+        return None;
+    }
+
+    let mut token = None;
+    let mut verifier = None;
+    for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+        let value = percent_decode(value);
+        match key {
+            "oauth_token" => token = Some(value),
+            "oauth_verifier" => verifier = Some(value),
+            // Twitter passes the token of the request that was cancelled
+            "denied" => return Some(Callback::Denied { token: value }),
+            _ => {}
+        }
+    }
+    Some(Callback::Authorized {
+        token: token?,
+        verifier: verifier?,
+    })
+}
+
+/// Decode a query value, where `+` is a space and `%XX` a byte of UTF-8. Invalid escapes are kept
+/// as they are.
+fn percent_decode(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' if tail.len() >= 2 && tail[..2].iter().all(u8::is_ascii_hexdigit) => {
+                let hex = std::str::from_utf8(&tail[..2]).unwrap();
+                bytes.push(u8::from_str_radix(hex, 16).unwrap());
+                rest = &tail[2..];
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egg_mode::KeyPair;
+    use std::time::Instant;
+
+    fn request(token: &'static str) -> AuthRequest {
+        AuthRequest {
+            consumer: KeyPair::new("consumer", "secret"),
+            token: KeyPair::new(token, "secret"),
+            started: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn authorized() {
+        let line = "GET /callback?oauth_token=abc&oauth_verifier=xyz HTTP/1.1";
+        assert_eq!(
+            parse_callback(line, &request("abc")),
+            Some(Callback::Authorized {
+                token: String::from("abc"),
+                verifier: String::from("xyz"),
+            })
+        );
+    }
+
+    #[test]
+    fn denied() {
+        let line = "GET /callback?denied=abc HTTP/1.1";
+        assert_eq!(
+            parse_callback(line, &request("abc")),
+            Some(Callback::Denied {
+                token: String::from("abc"),
+            })
+        );
+    }
+
+    #[test]
+    fn other_requests_are_ignored() {
+        let authorized = "GET /callback?oauth_token=abc&oauth_verifier=xyz HTTP/1.1";
+        let denied = "GET /callback?denied=abc HTTP/1.1";
+        assert_eq!(parse_callback(authorized, &request("def")), None);
+        assert_eq!(parse_callback(denied, &request("def")), None);
+    }
+
+    #[test]
+    fn values_are_decoded() {
+        let line = "GET /callback?oauth_token=a%2Bb%3D&oauth_verifier=x+y%zz HTTP/1.1";
+        assert_eq!(
+            parse_callback(line, &request("a+b=")),
+            Some(Callback::Authorized {
+                token: String::from("a+b="),
+                verifier: String::from("x y%zz"),
+            })
+        );
+        assert_eq!(percent_decode("%C3%A9%"), "é%");
+    }
+
+    #[test]
+    fn malformed_request_lines() {
+        for line in [
+            "",
+            "GET",
+            "POST /callback?oauth_token=abc&oauth_verifier=xyz HTTP/1.1",
+            "GET /favicon.ico HTTP/1.1",
+            "GET /callback HTTP/1.1",
+            "GET /callback?oauth_token=abc HTTP/1.1",
+            "GET /callback?oauth_verifier=xyz HTTP/1.1",
+            "GET /callback?oauth_token&oauth_verifier HTTP/1.1",
+        ] {
+            assert_eq!(parse_request_line(line), None, "{:?}", line);
+        }
+    }
+}
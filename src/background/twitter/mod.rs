@@ -1,24 +1,49 @@
+mod callback;
+
+pub use self::callback::{Callback, CallbackListener, DEFAULT_PORT as DEFAULT_CALLBACK_PORT};
 use egg_mode::{error::Result, *};
-use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+/// How long a login request stays valid after opening the login page
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
 // Generated in build.rs
 include!(concat!(env!("OUT_DIR"), "/twitter_credentials.rs"));
 
-pub const CONSUMER: KeyPair = KeyPair {
-    key: Cow::Borrowed(twitter_id()),
-    secret: Cow::Borrowed(twitter_secret()),
-};
+/// The consumer key pair that was provided when the application was built, if any.
+pub fn default_consumer() -> Option<KeyPair> {
+    Some(KeyPair::new(twitter_id()?, twitter_secret()?))
+}
 
 #[derive(Clone)]
 pub struct AuthRequest {
+    consumer: KeyPair,
     token: KeyPair,
+    started: Instant,
 }
 
 impl AuthRequest {
-    pub async fn new() -> Self {
-        Self {
-            token: auth::request_token(&CONSUMER, "oob").await.unwrap(),
-        }
+    /// Start a new login request. `callback` is the url that twitter redirects to after the user
+    /// authorized us, or `"oob"` if the user has to enter a PIN instead.
+    pub async fn new(consumer: KeyPair, callback: impl Into<String>) -> Result<Self> {
+        Ok(Self {
+            token: auth::request_token(&consumer, callback).await?,
+            consumer,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn expires_at(&self) -> Instant {
+        self.started + AUTH_TIMEOUT
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.started.elapsed() > AUTH_TIMEOUT
+    }
+
+    /// Returns `true` if the given oauth token belongs to this request.
+    pub fn matches(&self, token: &str) -> bool {
+        self.token.key == token
     }
 
     pub fn url(&self) -> String {
@@ -26,7 +51,7 @@ impl AuthRequest {
     }
 
     pub async fn authenticate(self, pin: String) -> Result<User> {
-        let (token, id, name) = auth::access_token(CONSUMER.clone(), &self.token, pin).await?;
+        let (token, id, name) = auth::access_token(self.consumer, &self.token, pin).await?;
         Ok(User { token, id, name })
     }
 }
@@ -40,10 +65,111 @@ pub struct User {
 
 impl User {
     pub async fn login_with_token(token: Token) -> Result<Self> {
+        if let Token::Bearer(_) = token {
+            // Bearer tokens can't be verified, as they don't belong to a user
+            return Ok(Self::guest(token));
+        }
         auth::verify_tokens(&token).await.map(|r| Self {
             token,
             id: r.response.id,
-            name: r.response.name,
+            name: r.response.screen_name,
         })
     }
+
+    /// A user for browsing without logging in, using a bearer token of the application.
+    pub fn guest(token: Token) -> Self {
+        Self {
+            token,
+            id: 0,
+            name: String::from("guest"),
+        }
+    }
+
+    /// Returns `true` if this is not an actual user, but we're browsing with an application token.
+    pub fn is_guest(&self) -> bool {
+        matches!(self.token, Token::Bearer(_))
+    }
+
+    /// Revoke the access token of this user, so it can't be used anymore.
+    /// Bearer tokens belong to the application instead of the user, so these are left alone.
+    pub async fn invalidate_token(&self) -> Result<()> {
+        if let Token::Access { .. } = self.token {
+            let request = raw::request_post(INVALIDATE_TOKEN, &self.token, None);
+            raw::response_raw_bytes(request).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors that can happen while logging in
+#[derive(Debug)]
+pub enum LoginError {
+    /// Could not get a request token, so the login page can't be opened
+    RequestToken(String),
+    /// Twitter did not accept the PIN or the callback
+    Authenticate(String),
+    /// The user declined the login in the browser
+    Denied,
+    /// The login page was opened more than `AUTH_TIMEOUT` ago
+    TimedOut,
+}
+
+impl LoginError {
+    /// Returns `true` if the login has to be started again after this error.
+    /// Otherwise the user can retry entering their PIN.
+    pub fn restarts_login(&self) -> bool {
+        !matches!(self, Self::Authenticate(_))
+    }
+}
+
+impl std::fmt::Display for LoginError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::RequestToken(e) => write!(fmt, "Could not start logging in: {}", e),
+            Self::Authenticate(e) => write!(fmt, "Could not log in: {}", e),
+            Self::Denied => write!(fmt, "Login was cancelled in the browser"),
+            Self::TimedOut => write!(fmt, "Login took too long, please try again"),
+        }
+    }
+}
+
+const INVALIDATE_TOKEN: &str = "https://api.twitter.com/1.1/oauth/invalidate_token";
+
+/// Returns `true` if we made too many requests and have to wait before trying again.
+pub fn is_rate_limited(error: &error::Error) -> bool {
+    match error {
+        error::Error::RateLimit(_) => true,
+        // 88: Rate limit exceeded
+        error::Error::TwitterError(_, errors) => errors.errors.iter().any(|e| e.code == 88),
+        error::Error::BadStatus(status) => status.as_u16() == 429,
+        _ => false,
+    }
+}
+
+/// The unix timestamp at which we're allowed to make requests again after being rate limited, if
+/// Twitter told us.
+pub fn rate_limit_reset(error: &error::Error) -> Option<i32> {
+    match error {
+        error::Error::RateLimit(reset) => Some(*reset),
+        error::Error::TwitterError(headers, _) => headers
+            .get("x-rate-limit-reset")?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok(),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the error indicates that the token we used has been revoked or has expired.
+pub fn is_unauthorized(error: &error::Error) -> bool {
+    match error {
+        // 32: Could not authenticate you
+        // 89: Invalid or expired token
+        error::Error::TwitterError(_, errors) => {
+            errors.errors.iter().any(|e| e.code == 32 || e.code == 89)
+        }
+        error::Error::BadStatus(status) => status.as_u16() == 401,
+        _ => false,
+    }
 }
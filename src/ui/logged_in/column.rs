@@ -0,0 +1,184 @@
+use super::{
+    list::{self, ListSettings},
+    profile,
+};
+use crate::background::{Background, Gap, TimelineKind, TweetAction};
+use egg_mode::{list::List, tweet::Tweet, user::TwitterUser};
+use egui::*;
+use std::collections::VecDeque;
+
+/// A single column of the deck, showing one timeline
+pub struct Column {
+    pub timeline: TimelineKind,
+    pub tweets: VecDeque<Tweet>,
+    /// Tweets that were polled in the background, these are only shown once the user asks for
+    /// them so the list doesn't move while they are reading it
+    pub new_tweets: Vec<Tweet>,
+    /// Places in the timeline where tweets may be missing
+    pub gaps: Vec<Gap>,
+    pub loading_more: bool,
+    /// Loading failed, older tweets aren't loaded while scrolling until the user asks for them or
+    /// another load succeeds
+    pub load_failed: bool,
+    /// There are no older tweets to load
+    pub reached_end: bool,
+    /// The user whose tweets or likes are being shown
+    pub profile: Option<Box<TwitterUser>>,
+}
+
+/// Something the user did in a column
+pub enum ColumnAction {
+    Activate,
+    Close,
+    Open(TimelineKind),
+    Select(Box<Tweet>),
+    /// Like or retweet the tweet with the given id
+    Tweet(u64, TweetAction),
+}
+
+impl Column {
+    pub fn new(background: &mut Background, timeline: TimelineKind) -> Self {
+        let mut result = Self {
+            timeline: timeline.clone(),
+            tweets: VecDeque::new(),
+            new_tweets: Vec::new(),
+            gaps: Vec::new(),
+            loading_more: false,
+            load_failed: false,
+            reached_end: false,
+            profile: None,
+        };
+        result.open(background, timeline);
+        result
+    }
+
+    /// Show a different timeline in this column
+    pub fn open(&mut self, background: &mut Background, timeline: TimelineKind) {
+        match &timeline {
+            TimelineKind::Search(query) => background.search(query.clone()),
+            _ => background.load_initial(timeline.clone()),
+        }
+        match (timeline.screen_name(), &self.profile) {
+            // Keep the profile when switching between the tweets and likes of a user
+            (Some(name), Some(profile)) if name.eq_ignore_ascii_case(&profile.screen_name) => {}
+            (Some(name), _) => {
+                self.profile = None;
+                background.load_profile(name.to_owned());
+            }
+            (None, _) => self.profile = None,
+        }
+        self.timeline = timeline;
+        self.tweets.clear();
+        self.new_tweets.clear();
+        self.gaps.clear();
+        self.loading_more = true;
+        self.load_failed = false;
+        self.reached_end = false;
+    }
+
+    pub fn add_polled_tweets(&mut self, tweets: Vec<Tweet>) {
+        if self.tweets.is_empty() {
+            // Nothing to keep in place
+            self.add_tweets(tweets);
+        } else {
+            // Tweets the user posted are shown right away, and polled again later
+            for tweet in tweets {
+                let shown = self
+                    .tweets
+                    .binary_search_by_key(&tweet.id, |t| t.id)
+                    .is_ok();
+                if !shown && !self.new_tweets.iter().any(|t| t.id == tweet.id) {
+                    self.new_tweets.push(tweet);
+                }
+            }
+        }
+    }
+
+    pub fn show_new_tweets(&mut self) {
+        let tweets = std::mem::take(&mut self.new_tweets);
+        self.add_tweets(tweets);
+    }
+
+    pub fn load_newer(&mut self, background: &mut Background) {
+        self.show_new_tweets();
+        background.load_newer(self.timeline.clone());
+        self.loading_more = true;
+    }
+
+    pub fn load_older(&mut self, background: &mut Background) {
+        background.load_older(self.timeline.clone());
+        self.loading_more = true;
+        self.load_failed = false;
+    }
+
+    pub fn add_tweets(&mut self, tweets: Vec<Tweet>) {
+        for tweet in tweets {
+            match self.tweets.binary_search_by_key(&tweet.id, |t| t.id) {
+                Ok(idx) => self.tweets[idx] = tweet,
+                Err(idx) => self.tweets.insert(idx, tweet),
+            }
+        }
+        self.loading_more = false;
+        self.load_failed = false;
+    }
+
+    pub fn title(&self, lists: &[List]) -> String {
+        match &self.timeline {
+            TimelineKind::Home => String::from("Home"),
+            TimelineKind::Mentions => String::from("Mentions"),
+            TimelineKind::User(name) => format!("@{}", name),
+            TimelineKind::Likes(name) => format!("Likes of @{}", name),
+            TimelineKind::Search(query) => format!("Search: {}", query),
+            TimelineKind::List(id) => lists
+                .iter()
+                .find(|l| l.id == *id)
+                .map_or_else(|| String::from("List"), |l| l.full_name.clone()),
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        ctx: &mut crate::Context,
+        ui: &mut Ui,
+        title: String,
+        active: bool,
+        settings: &ListSettings,
+        expanded_tweet: &Option<Tweet>,
+    ) -> Option<ColumnAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            if ui.selectable_label(active, title).clicked() {
+                action = Some(ColumnAction::Activate);
+            }
+            if ui.small_button("x").on_hover_text("Close column").clicked() {
+                action = Some(ColumnAction::Close);
+            }
+        });
+        ui.separator();
+        if !self.new_tweets.is_empty() {
+            let text = match self.new_tweets.len() {
+                1 => String::from("1 new tweet"),
+                count => format!("{} new tweets", count),
+            };
+            if ui.button(text).clicked() {
+                self.show_new_tweets();
+            }
+        }
+        if let Some(profile) = &self.profile {
+            profile::draw_profile(ctx, ui, profile);
+            ui.horizontal(|ui| {
+                for (timeline, title) in [
+                    (TimelineKind::User(profile.screen_name.clone()), "Tweets"),
+                    (TimelineKind::Likes(profile.screen_name.clone()), "Likes"),
+                ] {
+                    let selected = self.timeline == timeline;
+                    if ui.selectable_label(selected, title).clicked() && !selected {
+                        action = Some(ColumnAction::Open(timeline));
+                    }
+                }
+            });
+            ui.separator();
+        }
+        list::tweet_list(self, settings, expanded_tweet, ctx.background, ui).or(action)
+    }
+}
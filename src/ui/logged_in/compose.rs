@@ -0,0 +1,836 @@
+use super::split::{join_thread, split_thread, ThreadEdit};
+use crate::{
+    background::{
+        numbered, unix_now, Attachment, Draft, MediaKind, Mention, NewTweet, Preview, QuotedTweet,
+        ReplyTarget, UploadStage, MAX_IMAGES,
+    },
+    ui::utils::{FilePicker, FilePickerAction, Image},
+};
+use chrono::{Local, NaiveDateTime, TimeZone};
+use egg_mode::tweet::Tweet;
+use egui::*;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// The maximum weighted length of a tweet
+pub const MAX_WEIGHTED_LENGTH: usize = 280;
+/// Links are shortened by Twitter, so they always count as this many characters
+const URL_LENGTH: usize = 23;
+/// The size of the previews of attached images
+const PREVIEW_SIZE: f32 = 64.;
+/// How scheduled times are entered and shown, in local time
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+/// The draft is saved once it's left unchanged for this long, not on every keystroke
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// The panel to write a new tweet in
+#[derive(Default)]
+pub struct Compose {
+    /// Everything the user entered, this is saved while they type
+    pub draft: Draft,
+    /// Waiting for Twitter to accept the tweet
+    pub posting: bool,
+    pub error: Option<String>,
+    /// The attachment that is being uploaded, and how far along it is
+    pub upload: Option<(usize, UploadStage)>,
+    picker: Option<FilePicker>,
+    /// The previews of attached images and GIFs, `None` while they are loading
+    previews: HashMap<PathBuf, Option<Result<Preview, String>>>,
+    /// When to post the tweet as entered by the user, `None` to post it right away
+    schedule: Option<String>,
+    /// The text field was focused when the panel opened
+    focused: bool,
+    /// When the draft was last changed, if that was after it was last saved
+    changed: Option<Instant>,
+}
+
+pub enum ComposeAction {
+    Post,
+    /// Post the tweet at the given unix timestamp
+    Schedule(u64),
+    /// Close the composer, keeping the draft
+    Close,
+    /// Close the composer and delete the draft
+    Discard,
+}
+
+impl Compose {
+    /// Reply to the given tweet. `own_id` is the id of the logged in user, who is never mentioned.
+    pub fn reply(tweet: Tweet, own_id: u64) -> Self {
+        let author = tweet.user.as_ref().map(|u| u.id);
+        let mut mentions: Vec<Mention> = Vec::new();
+        for mention in &tweet.entities.user_mentions {
+            if Some(mention.id) != author
+                && mention.id != own_id
+                && !mentions.iter().any(|m| m.id == mention.id)
+            {
+                mentions.push(Mention {
+                    id: mention.id,
+                    screen_name: mention.screen_name.clone(),
+                    selected: true,
+                });
+            }
+        }
+        let reply = ReplyTarget {
+            id: tweet.id,
+            screen_name: screen_name(&tweet),
+            text: tweet.text,
+            mentions,
+        };
+        Self::from_draft(Draft {
+            reply: Some(reply),
+            ..Draft::new()
+        })
+    }
+
+    pub fn quote(tweet: Tweet) -> Self {
+        let quote = QuotedTweet {
+            id: tweet.id,
+            screen_name: screen_name(&tweet),
+            text: tweet.text,
+        };
+        Self::from_draft(Draft {
+            quote: Some(quote),
+            ..Draft::new()
+        })
+    }
+
+    /// Continue writing a saved draft
+    pub fn from_draft(draft: Draft) -> Self {
+        Self {
+            draft,
+            ..Default::default()
+        }
+    }
+
+    /// Edit a tweet that is scheduled to be posted at the given unix timestamp
+    pub fn scheduled(draft: Draft, post_at: u64) -> Self {
+        Self {
+            schedule: Some(format_time(post_at)),
+            ..Self::from_draft(draft)
+        }
+    }
+
+    /// The draft, if it changed since it was last saved and was then left alone for a moment
+    pub fn take_changes(&mut self) -> Option<Draft> {
+        if self.changed?.elapsed() < SAVE_DELAY {
+            return None;
+        }
+        self.flush_changes()
+    }
+
+    /// The draft, if it changed since it was last saved
+    pub fn flush_changes(&mut self) -> Option<Draft> {
+        self.changed.take()?;
+        self.draft.saved_at = unix_now();
+        Some(self.draft.clone())
+    }
+
+    /// The draft changed, but isn't saved yet
+    pub fn has_changes(&self) -> bool {
+        self.changed.is_some()
+    }
+
+    pub fn new_tweet(&self) -> NewTweet {
+        self.draft.tweet()
+    }
+
+    /// Attach a file to the tweet, or show why it can't be attached
+    pub fn attach(&mut self, path: PathBuf) {
+        if let Err(error) = self.check_attachment(&path) {
+            self.error = Some(error);
+            return;
+        }
+        if !self.draft.media.iter().any(|m| m.path == path) {
+            self.draft.media.push(Attachment::new(path));
+            self.changed = Some(Instant::now());
+        }
+    }
+
+    fn check_attachment(&self, path: &std::path::Path) -> Result<(), String> {
+        let attachment = Attachment::new(path.to_path_buf());
+        let kind = attachment
+            .kind()
+            .ok_or_else(|| format!("{} is not an image, GIF or video", attachment.file_name()))?;
+        let size = std::fs::metadata(path)
+            .map_err(|e| format!("Could not read {}: {}", attachment.file_name(), e))?
+            .len();
+        if size > kind.max_size() {
+            return Err(format!(
+                "{} is too large, the limit is {} MB",
+                attachment.file_name(),
+                kind.max_size() / 1024 / 1024
+            ));
+        }
+        let only_images = self
+            .draft
+            .media
+            .iter()
+            .all(|m| matches!(m.kind(), Some(kind) if kind.is_image()));
+        if !self.draft.media.is_empty() && (!kind.is_image() || !only_images) {
+            return Err(String::from(
+                "GIFs and videos have to be attached on their own",
+            ));
+        }
+        if self.draft.media.len() >= MAX_IMAGES {
+            return Err(format!("Up to {} images can be attached", MAX_IMAGES));
+        }
+        Ok(())
+    }
+
+    /// The background decoded the preview of an attachment
+    pub fn set_preview(&mut self, path: PathBuf, preview: Result<Preview, String>) {
+        if let Some(entry) = self.previews.get_mut(&path) {
+            *entry = Some(preview);
+        }
+    }
+
+    /// One of the tweets of the thread that is being posted was posted, the background already
+    /// stored this with the draft
+    pub fn thread_tweet_posted(&mut self, id: u64) {
+        self.draft.posted.push(id);
+    }
+
+    /// The weighted length of every tweet of the thread, including its number
+    fn thread_lengths(&self) -> impl Iterator<Item = usize> + '_ {
+        let count = self.draft.thread.len();
+        self.draft
+            .thread
+            .iter()
+            .enumerate()
+            .map(move |(index, text)| weighted_length(&numbered(text, index, count)))
+    }
+
+    /// Draw a text field for every tweet of a thread, with buttons to move the split points.
+    /// Returns whether anything changed.
+    fn draw_thread(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        let mut edit = None;
+        let lengths: Vec<usize> = self.thread_lengths().collect();
+        let count = self.draft.thread.len();
+        let posted = self.draft.posted.len();
+        // The tweets that were posted are numbered out of the current count, so it can't change
+        // anymore
+        let thread = self.draft.thread.clone();
+        let allowed = |edit: ThreadEdit| posted == 0 || edit.keeps_count(&thread);
+        ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+            for (index, text) in self.draft.thread.iter_mut().enumerate() {
+                let editable = !self.posting && index >= posted;
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("{}/{}", index + 1, count)).strong());
+                    let counter = format!("{}/{}", lengths[index], MAX_WEIGHTED_LENGTH);
+                    if lengths[index] > MAX_WEIGHTED_LENGTH {
+                        ui.colored_label(Color32::RED, counter);
+                    } else {
+                        ui.label(counter);
+                    }
+                    if index < posted {
+                        ui.label(RichText::new("posted").weak());
+                    }
+                    if !editable {
+                        return;
+                    }
+                    if index > posted
+                        && allowed(ThreadEdit::MoveUp(index))
+                        && ui
+                            .small_button("⬆")
+                            .on_hover_text("Move the first sentence to the previous tweet")
+                            .clicked()
+                    {
+                        edit = Some(ThreadEdit::MoveUp(index));
+                    }
+                    if index + 1 < count {
+                        if allowed(ThreadEdit::MoveDown(index))
+                            && ui
+                                .small_button("⬇")
+                                .on_hover_text("Move the last sentence to the next tweet")
+                                .clicked()
+                        {
+                            edit = Some(ThreadEdit::MoveDown(index));
+                        }
+                        if allowed(ThreadEdit::Merge(index))
+                            && ui
+                                .small_button("Merge")
+                                .on_hover_text("Merge with the next tweet")
+                                .clicked()
+                        {
+                            edit = Some(ThreadEdit::Merge(index));
+                        }
+                    }
+                    if lengths[index] > MAX_WEIGHTED_LENGTH
+                        && allowed(ThreadEdit::Split(index))
+                        && ui.small_button("Split").clicked()
+                    {
+                        edit = Some(ThreadEdit::Split(index));
+                    }
+                });
+                changed |= ui
+                    .add_enabled(editable, TextEdit::multiline(text).desired_rows(3))
+                    .changed();
+            }
+        });
+        if let Some(edit) = edit {
+            edit.apply(&mut self.draft.thread);
+            changed = true;
+        }
+        if posted == 0
+            && ui
+                .add_enabled(!self.posting, Button::new("Back to a single tweet"))
+                .clicked()
+        {
+            self.draft.text = join_thread(&self.draft.thread);
+            self.draft.thread.clear();
+            changed = true;
+        }
+        changed
+    }
+
+    /// Whether another image can be attached
+    fn can_attach(&self) -> bool {
+        self.draft.posted.is_empty()
+            && self.draft.media.len() < MAX_IMAGES
+            && self
+                .draft
+                .media
+                .iter()
+                .all(|m| matches!(m.kind(), Some(kind) if kind.is_image()))
+    }
+
+    fn title(&self) -> &'static str {
+        if self.draft.reply.is_some() {
+            "Reply"
+        } else if self.draft.quote.is_some() {
+            "Quote tweet"
+        } else {
+            "New tweet"
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &mut crate::Context) -> Option<ComposeAction> {
+        let mut action = None;
+        Window::new(self.title())
+            .id(Id::new("compose"))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx.ctx, |ui| {
+                let mut changed = false;
+                if let Some(reply) = &mut self.draft.reply {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Replying to");
+                        ui.label(RichText::new(format!("@{}", reply.screen_name)).strong());
+                        for mention in &mut reply.mentions {
+                            let label = format!("@{}", mention.screen_name);
+                            changed |= ui.checkbox(&mut mention.selected, label).changed();
+                        }
+                    });
+                    ui.label(RichText::new(&reply.text).weak());
+                    ui.separator();
+                }
+                let thread = !self.draft.thread.is_empty();
+                if thread {
+                    changed |= self.draw_thread(ui);
+                } else {
+                    let response = ui.add_enabled(
+                        !self.posting,
+                        TextEdit::multiline(&mut self.draft.text).desired_rows(4),
+                    );
+                    changed |= response.changed();
+                    if !self.focused {
+                        response.request_focus();
+                        self.focused = true;
+                    }
+                }
+                // Attachments belong to the first tweet of a thread
+                let can_edit_media = !self.posting && self.draft.posted.is_empty();
+                let mut remove = None;
+                for (index, attachment) in self.draft.media.iter_mut().enumerate() {
+                    let kind = attachment.kind();
+                    // Videos can't be decoded, so they only show their file name
+                    let preview = match kind {
+                        Some(MediaKind::Mp4) | None => None,
+                        Some(_) => {
+                            Some(self.previews.entry(attachment.path.clone()).or_insert_with(
+                                || {
+                                    ctx.background.load_preview(attachment.path.clone());
+                                    None
+                                },
+                            ))
+                        }
+                    };
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            if let Some(preview) = preview {
+                                draw_preview(ui, preview);
+                            }
+                            ui.vertical(|ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        RichText::new(kind.map_or("File", MediaKind::name))
+                                            .strong(),
+                                    );
+                                    ui.label(attachment.file_name());
+                                    if ui
+                                        .add_enabled(can_edit_media, Button::new("x").small())
+                                        .on_hover_text("Remove")
+                                        .clicked()
+                                    {
+                                        remove = Some(index);
+                                    }
+                                });
+                                // Twitter only accepts descriptions of images and GIFs
+                                if kind != Some(MediaKind::Mp4) {
+                                    changed |= ui
+                                        .add_enabled(
+                                            can_edit_media,
+                                            TextEdit::singleline(&mut attachment.alt_text)
+                                                .hint_text(
+                                                    "Description for people who can't see it",
+                                                ),
+                                        )
+                                        .changed();
+                                }
+                            });
+                        });
+                    });
+                }
+                if let Some(index) = remove {
+                    let attachment = self.draft.media.remove(index);
+                    self.previews.remove(&attachment.path);
+                    changed = true;
+                }
+                if !self.posting && !ctx.ctx.input().raw.hovered_files.is_empty() {
+                    ui.label(RichText::new("Drop the files to attach them").weak());
+                }
+                if thread {
+                    // Threads are posted right away
+                    self.schedule = None;
+                } else {
+                    ui.horizontal(|ui| {
+                        let mut scheduled = self.schedule.is_some();
+                        if ui
+                            .add_enabled(!self.posting, Checkbox::new(&mut scheduled, "Post at"))
+                            .changed()
+                        {
+                            self.schedule = scheduled.then(|| {
+                                let in_an_hour = Local::now().timestamp() as u64 + 60 * 60;
+                                format_time(in_an_hour)
+                            });
+                        }
+                        if let Some(schedule) = &mut self.schedule {
+                            ui.add(TextEdit::singleline(schedule).desired_width(120.))
+                                .on_hover_text("Local time, as year-month-day hour:minute");
+                        }
+                    });
+                }
+                let post_at = self.schedule.as_deref().map(parse_time);
+                if let Some(Err(error)) = &post_at {
+                    ui.colored_label(Color32::RED, error);
+                }
+                let length = weighted_length(&self.draft.text);
+                let (too_long, has_content) = if thread {
+                    let posted = self.draft.posted.len();
+                    let remaining = self.draft.thread.get(posted..).unwrap_or_default();
+                    (
+                        self.thread_lengths().any(|l| l > MAX_WEIGHTED_LENGTH),
+                        !remaining.is_empty() && remaining.iter().all(|t| !t.trim().is_empty()),
+                    )
+                } else {
+                    (
+                        length > MAX_WEIGHTED_LENGTH,
+                        !self.draft.text.trim().is_empty() || !self.draft.media.is_empty(),
+                    )
+                };
+                ui.horizontal(|ui| {
+                    if !thread {
+                        let counter = format!("{}/{}", length, MAX_WEIGHTED_LENGTH);
+                        if too_long {
+                            ui.colored_label(Color32::RED, counter);
+                            if ui
+                                .add_enabled(!self.posting, Button::new("Split into a thread"))
+                                .clicked()
+                            {
+                                self.draft.thread = split_thread(&self.draft.text);
+                                self.draft.text.clear();
+                                changed = true;
+                            }
+                        } else {
+                            ui.label(counter);
+                        }
+                    }
+                    let can_post = !self.posting && has_content && !too_long;
+                    match post_at {
+                        None => {
+                            let label = match (thread, self.draft.posted.len()) {
+                                (false, _) => String::from("Tweet"),
+                                (true, 0) => format!("Post {} tweets", self.draft.thread.len()),
+                                (true, posted) => format!(
+                                    "Resume ({} of {} posted)",
+                                    posted,
+                                    self.draft.thread.len()
+                                ),
+                            };
+                            if ui.add_enabled(can_post, Button::new(label)).clicked() {
+                                action = Some(ComposeAction::Post);
+                            }
+                        }
+                        Some(post_at) => {
+                            let button = Button::new("Schedule");
+                            if ui
+                                .add_enabled(can_post && post_at.is_ok(), button)
+                                .clicked()
+                            {
+                                action = post_at.ok().map(ComposeAction::Schedule);
+                            }
+                        }
+                    }
+                    let can_attach = !self.posting && self.can_attach();
+                    if ui
+                        .add_enabled(can_attach, Button::new("Add media"))
+                        .clicked()
+                    {
+                        let directory = self.draft.media.last().and_then(|m| m.path.parent());
+                        self.picker = Some(FilePicker::new(
+                            "Attach media",
+                            MediaKind::EXTENSIONS,
+                            directory,
+                        ));
+                    }
+                    if ui
+                        .button("Close")
+                        .on_hover_text("Keep the draft to finish it later")
+                        .clicked()
+                    {
+                        action = Some(ComposeAction::Close);
+                    }
+                    if ui
+                        .add_enabled(!self.posting, Button::new("Discard"))
+                        .clicked()
+                    {
+                        action = Some(ComposeAction::Discard);
+                    }
+                    if self.posting {
+                        ui.label("Posting...");
+                    }
+                });
+                if let (true, Some((index, stage))) = (self.posting, self.upload) {
+                    let name = self
+                        .draft
+                        .media
+                        .get(index)
+                        .map(Attachment::file_name)
+                        .unwrap_or_default();
+                    let (done, text) = match stage {
+                        UploadStage::Uploading => (0., format!("Uploading {}", name)),
+                        UploadStage::Processing => (0.5, format!("Processing {}", name)),
+                        UploadStage::Done => (1., format!("Uploaded {}", name)),
+                    };
+                    let progress = (index as f32 + done) / self.draft.media.len().max(1) as f32;
+                    ui.add(
+                        ProgressBar::new(progress)
+                            .text(text)
+                            .animate(stage == UploadStage::Processing),
+                    );
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                if let Some(quote) = &self.draft.quote {
+                    ui.separator();
+                    ui.label(RichText::new(format!("Quoting @{}", quote.screen_name)).strong());
+                    ui.label(RichText::new(&quote.text).weak());
+                }
+                if changed {
+                    self.changed = Some(Instant::now());
+                }
+            });
+        if let Some(picker) = &mut self.picker {
+            match picker.draw(ctx) {
+                Some(FilePickerAction::Pick(path)) => {
+                    self.picker = None;
+                    self.attach(path);
+                }
+                Some(FilePickerAction::Close) => self.picker = None,
+                None => {}
+            }
+        }
+        action
+    }
+}
+
+fn draw_preview(ui: &mut Ui, preview: &Option<Result<Preview, String>>) {
+    match preview {
+        Some(Ok(preview)) => {
+            ui.add(Image::preview(preview, PREVIEW_SIZE));
+        }
+        Some(Err(error)) => {
+            ui.label(RichText::new("No preview").weak())
+                .on_hover_text(error.as_str());
+        }
+        None => {
+            ui.label(RichText::new("Loading preview").weak());
+        }
+    }
+}
+
+/// Show a unix timestamp in local time
+pub fn format_time(timestamp: u64) -> String {
+    Local
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map_or_else(String::new, |time| time.format(TIME_FORMAT).to_string())
+}
+
+/// Parse a time in the future that the user entered, returning the unix timestamp
+fn parse_time(text: &str) -> Result<u64, String> {
+    let time = NaiveDateTime::parse_from_str(text.trim(), TIME_FORMAT)
+        .ok()
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+        .ok_or_else(|| String::from("Enter the time like 2022-06-30 14:00"))?;
+    if time <= Local::now() {
+        return Err(String::from("This time has already passed"));
+    }
+    Ok(time.timestamp() as u64)
+}
+
+/// A single line describing a draft, for the list of drafts
+pub fn summary(draft: &Draft) -> String {
+    const MAX_LENGTH: usize = 30;
+    let text = draft.thread.first().unwrap_or(&draft.text);
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty());
+    if let Some(line) = line {
+        if line.chars().count() > MAX_LENGTH {
+            let start: String = line.chars().take(MAX_LENGTH).collect();
+            return format!("{}...", start.trim_end());
+        }
+        return line.to_owned();
+    }
+    if let Some(reply) = &draft.reply {
+        format!("Reply to @{}", reply.screen_name)
+    } else if let Some(quote) = &draft.quote {
+        format!("Quote of @{}", quote.screen_name)
+    } else {
+        match draft.media.len() {
+            1 => String::from("1 attachment"),
+            count => format!("{} attachments", count),
+        }
+    }
+}
+
+/// The screen name of the author, or a placeholder that twitter.com redirects correctly
+fn screen_name(tweet: &Tweet) -> String {
+    tweet
+        .user
+        .as_ref()
+        .map_or_else(|| String::from("i"), |u| u.screen_name.clone())
+}
+
+/// The length of a tweet as Twitter counts it: links count as 23 characters, and most characters
+/// outside of Latin scripts (like CJK and emoji) count as two.
+pub fn weighted_length(text: &str) -> usize {
+    let words: usize = text
+        .split_whitespace()
+        .map(|word| match find_url(word) {
+            Some((start, end)) => {
+                word_length(&word[..start]) + URL_LENGTH + word_length(&word[end..])
+            }
+            None => word_length(word),
+        })
+        .sum();
+    words + text.chars().filter(|c| c.is_whitespace()).count()
+}
+
+/// Top level domains that Twitter links without a scheme, besides the country codes. This isn't
+/// the full list Twitter uses, but covers the ones people actually type.
+const GENERIC_TLDS: &[&str] = &[
+    "aero", "app", "art", "asia", "biz", "blog", "cat", "cloud", "club", "com", "coop", "design",
+    "dev", "edu", "email", "gov", "info", "int", "jobs", "link", "live", "mil", "mobi", "museum",
+    "name", "net", "news", "online", "org", "page", "post", "pro", "shop", "site", "store", "tech",
+    "tel", "top", "travel", "xxx", "xyz",
+];
+
+/// Every country code top level domain
+const COUNTRY_TLDS: &[&str] = &[
+    "ac", "ad", "ae", "af", "ag", "ai", "al", "am", "ao", "aq", "ar", "as", "at", "au", "aw", "ax",
+    "az", "ba", "bb", "bd", "be", "bf", "bg", "bh", "bi", "bj", "bm", "bn", "bo", "br", "bs", "bt",
+    "bv", "bw", "by", "bz", "ca", "cc", "cd", "cf", "cg", "ch", "ci", "ck", "cl", "cm", "cn", "co",
+    "cr", "cu", "cv", "cw", "cx", "cy", "cz", "de", "dj", "dk", "dm", "do", "dz", "ec", "ee", "eg",
+    "er", "es", "et", "eu", "fi", "fj", "fk", "fm", "fo", "fr", "ga", "gb", "gd", "ge", "gf", "gg",
+    "gh", "gi", "gl", "gm", "gn", "gp", "gq", "gr", "gs", "gt", "gu", "gw", "gy", "hk", "hm", "hn",
+    "hr", "ht", "hu", "id", "ie", "il", "im", "in", "io", "iq", "ir", "is", "it", "je", "jm", "jo",
+    "jp", "ke", "kg", "kh", "ki", "km", "kn", "kp", "kr", "kw", "ky", "kz", "la", "lb", "lc", "li",
+    "lk", "lr", "ls", "lt", "lu", "lv", "ly", "ma", "mc", "md", "me", "mg", "mh", "mk", "ml", "mm",
+    "mn", "mo", "mp", "mq", "mr", "ms", "mt", "mu", "mv", "mw", "mx", "my", "mz", "na", "nc", "ne",
+    "nf", "ng", "ni", "nl", "no", "np", "nr", "nu", "nz", "om", "pa", "pe", "pf", "pg", "ph", "pk",
+    "pl", "pm", "pn", "pr", "ps", "pt", "pw", "py", "qa", "re", "ro", "rs", "ru", "rw", "sa", "sb",
+    "sc", "sd", "se", "sg", "sh", "si", "sj", "sk", "sl", "sm", "sn", "so", "sr", "ss", "st", "su",
+    "sv", "sx", "sy", "sz", "tc", "td", "tf", "tg", "th", "tj", "tk", "tl", "tm", "tn", "to", "tp",
+    "tr", "tt", "tv", "tw", "tz", "ua", "ug", "uk", "us", "uy", "uz", "va", "vc", "ve", "vg", "vi",
+    "vn", "vu", "wf", "ws", "ye", "yt", "za", "zm", "zw",
+];
+
+/// Country codes that are linked without a scheme or path even if there's only a single label
+/// before them, like `t.co`
+const SPECIAL_COUNTRY_TLDS: &[&str] = &["co", "tv"];
+
+/// Find the link in a word the way Twitter does, returning where it starts and ends. Links
+/// without a scheme need a known top level domain, and punctuation around the link isn't part of
+/// it.
+fn find_url(word: &str) -> Option<(usize, usize)> {
+    let start = word
+        .char_indices()
+        .find(|(_, c)| c.is_alphanumeric())
+        .map(|(i, _)| i)?;
+    let before = &word[..start];
+    // Mentions, hashtags and cashtags are never links
+    if before.contains(&['@', '＠', '#', '＃', '$'][..]) {
+        return None;
+    }
+
+    let mut end = word.len();
+    for (i, c) in word[start..].char_indices().rev() {
+        let i = start + i;
+        let trailing = match c {
+            // Brackets are part of links like `https://en.wikipedia.org/wiki/Rust_(language)`
+            ')' => word[start..i].matches('(').count() <= word[start..i].matches(')').count(),
+            _ => !(c.is_alphanumeric() || matches!(c, '/' | '-' | '_' | '=' | '&' | '#' | '+')),
+        };
+        if !trailing {
+            break;
+        }
+        end = i;
+    }
+    let url = &word[start..end];
+
+    let lower = url.to_lowercase();
+    let rest = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"));
+    if let Some(rest) = rest {
+        // Twitter shortens every link with a scheme
+        if rest.is_empty() {
+            return None;
+        }
+        return Some((start, end));
+    }
+    if before.ends_with(&['-', '_', '.', '/'][..]) {
+        return None;
+    }
+    let host_end = lower.find(&['/', ':', '?', '#'][..]).unwrap_or(lower.len());
+    let (host, path) = lower.split_at(host_end);
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid_label = |label: &&str| {
+        !label.is_empty()
+            && !label.starts_with(&['-', '_'][..])
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if labels.len() < 2 || !labels.iter().all(valid_label) {
+        return None;
+    }
+    let tld = labels[labels.len() - 1];
+    let country = COUNTRY_TLDS.contains(&tld);
+    if !country && !GENERIC_TLDS.contains(&tld) {
+        return None;
+    }
+    // Things like `example.de` are only linked with a path, to avoid linking typos like `end.It`
+    if country && labels.len() == 2 && path.is_empty() && !SPECIAL_COUNTRY_TLDS.contains(&tld) {
+        return None;
+    }
+    Some((start, end))
+}
+
+fn word_length(word: &str) -> usize {
+    let mut length = 0;
+    let mut joined = false;
+    for c in word.chars() {
+        if joined {
+            // Part of an emoji sequence, which counts as a single emoji
+            joined = false;
+            continue;
+        }
+        match c as u32 {
+            // Zero width joiner, variation selectors and skin tone modifiers
+            0x200D => joined = true,
+            0xFE00..=0xFE0F | 0x1F3FB..=0x1F3FF => {}
+            0x0000..=0x10FF | 0x2000..=0x200C | 0x2010..=0x201F | 0x2032..=0x2037 => length += 1,
+            _ => length += 2,
+        }
+    }
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii() {
+        assert_eq!(weighted_length(""), 0);
+        assert_eq!(weighted_length("Hello, world!"), 13);
+        assert_eq!(weighted_length("two  spaces\nand a line"), 22);
+        assert_eq!(weighted_length("café — “quoted”"), 15);
+    }
+
+    #[test]
+    fn cjk() {
+        assert_eq!(weighted_length("こんにちは"), 10);
+        assert_eq!(weighted_length("你好 world"), 10);
+        assert_eq!(weighted_length("안녕"), 4);
+    }
+
+    #[test]
+    fn emoji() {
+        assert_eq!(word_length("👍"), 2);
+        assert_eq!(word_length("❤️"), 2);
+        // Skin tone modifiers don't count
+        assert_eq!(word_length("👍🏽"), 2);
+        // A family is joined with zero width joiners, and counts as a single emoji
+        assert_eq!(word_length("👨‍👩‍👧‍👦"), 2);
+        assert_eq!(word_length("👩🏽‍💻"), 2);
+        assert_eq!(weighted_length("hi 👋🏻👋🏿"), 7);
+    }
+
+    #[test]
+    fn urls_with_scheme() {
+        let long = "https://example.com/a/very/long/path/that/is/shortened/anyway";
+        assert_eq!(weighted_length(long), URL_LENGTH);
+        assert_eq!(weighted_length("http://x.y"), URL_LENGTH);
+        assert_eq!(weighted_length(&format!("see {}", long)), 4 + URL_LENGTH);
+        assert_eq!(weighted_length("HTTPS://EXAMPLE.COM"), URL_LENGTH);
+        assert_eq!(weighted_length("https://"), 8);
+    }
+
+    #[test]
+    fn urls_without_scheme() {
+        assert_eq!(weighted_length("example.com"), URL_LENGTH);
+        assert_eq!(weighted_length("www.example.org/about"), URL_LENGTH);
+        assert_eq!(weighted_length("t.co/x"), URL_LENGTH);
+        assert_eq!(weighted_length("t.co"), URL_LENGTH);
+        assert_eq!(weighted_length("news.bbc.co.uk"), URL_LENGTH);
+        assert_eq!(weighted_length("example.de/seite"), URL_LENGTH);
+        // Short country domains without a path aren't linked
+        assert_eq!(weighted_length("example.de"), 10);
+        assert_eq!(weighted_length("the end.It"), 10);
+        // Neither are unknown top level domains, abbreviations or email addresses
+        assert_eq!(weighted_length("notes.txt"), 9);
+        assert_eq!(weighted_length("e.g."), 4);
+        assert_eq!(weighted_length("me@example.com"), 14);
+        assert_eq!(weighted_length("@example.com"), 12);
+        assert_eq!(weighted_length("#example.com"), 12);
+    }
+
+    #[test]
+    fn urls_in_punctuation() {
+        assert_eq!(weighted_length("(https://example.com)"), URL_LENGTH + 2);
+        assert_eq!(weighted_length("example.com."), URL_LENGTH + 1);
+        assert_eq!(weighted_length("“example.com/x”,"), URL_LENGTH + 3);
+        assert_eq!(weighted_length("example.com/search?"), URL_LENGTH + 1);
+        let wiki = "https://en.wikipedia.org/wiki/Rust_(programming_language)";
+        assert_eq!(weighted_length(wiki), URL_LENGTH);
+        assert_eq!(weighted_length(&format!("({})", wiki)), URL_LENGTH + 2);
+    }
+}
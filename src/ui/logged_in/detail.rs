@@ -1,8 +1,29 @@
-use crate::ui::utils::Image;
-use egg_mode::tweet::Tweet;
+use crate::{
+    background::TweetAction,
+    ui::utils::{ClickableLink, Image},
+};
+use egg_mode::{tweet::Tweet, user::TwitterUser};
 use egui::*;
 
-pub fn draw_tweet(ctx: &mut crate::Context, ui: &mut Ui, tweet: &Tweet) {
+/// Something the user clicked on in the detail view
+pub enum Action {
+    OpenProfile(Box<TwitterUser>),
+    /// Load the conversation of the tweet with the given id
+    ShowConversation(u64),
+    Reply(Box<Tweet>),
+    Quote(Box<Tweet>),
+    /// Like or retweet the tweet with the given id
+    Tweet(u64, TweetAction),
+}
+
+/// Draw a tweet, `read_only` hides the buttons that need a logged in user
+pub fn draw_tweet(
+    ctx: &mut crate::Context,
+    ui: &mut Ui,
+    tweet: &Tweet,
+    read_only: bool,
+) -> Option<Action> {
+    let mut action = None;
     let user = tweet.user.as_ref().unwrap();
     ui.horizontal(|ui| {
         ui.add(Image::https(
@@ -13,10 +34,12 @@ pub fn draw_tweet(ctx: &mut crate::Context, ui: &mut Ui, tweet: &Tweet) {
         ui.vertical(|ui| {
             ui.label(RichText::new(&user.name).strong());
             ui.horizontal(|ui| {
-                ui.hyperlink_to(
-                    format!("@{}", user.screen_name),
-                    format!("https://twitter.com/{}", user.screen_name),
-                );
+                if ui
+                    .add(ClickableLink::new(format!("@{}", user.screen_name)))
+                    .clicked()
+                {
+                    action = Some(Action::OpenProfile(user.clone()));
+                }
                 ui.separator();
                 ui.hyperlink_to(
                     "original",
@@ -25,6 +48,15 @@ pub fn draw_tweet(ctx: &mut crate::Context, ui: &mut Ui, tweet: &Tweet) {
                         user.screen_name, tweet.id
                     ),
                 );
+                if tweet.retweeted_status.is_none() && !read_only {
+                    ui.separator();
+                    if ui.add(ClickableLink::new("reply")).clicked() {
+                        action = Some(Action::Reply(Box::new(tweet.clone())));
+                    }
+                    if ui.add(ClickableLink::new("quote")).clicked() {
+                        action = Some(Action::Quote(Box::new(tweet.clone())));
+                    }
+                }
             });
         });
     });
@@ -36,7 +68,7 @@ pub fn draw_tweet(ctx: &mut crate::Context, ui: &mut Ui, tweet: &Tweet) {
     if let Some(nested) = &tweet.retweeted_status {
         ui.label(RichText::new("Retweeted:").strong());
         ui.separator();
-        draw_tweet(ctx, ui, nested);
+        action = draw_tweet(ctx, ui, nested, read_only).or(action);
     } else {
         ui.label(RichText::new(tweet.text.as_str()).strong());
         let max = ui.max_rect().size().min_elem();
@@ -62,8 +94,41 @@ pub fn draw_tweet(ctx: &mut crate::Context, ui: &mut Ui, tweet: &Tweet) {
         });
         // });
 
+        if !read_only {
+            if let Some(tweet_action) = tweet_actions(ui, tweet) {
+                action = Some(Action::Tweet(tweet.id, tweet_action));
+            }
+        }
+
         if let Some(quoted) = &tweet.quoted_status {
-            draw_tweet(ctx, ui, quoted);
+            action = draw_tweet(ctx, ui, quoted, read_only).or(action);
         }
     }
+    action
+}
+
+/// The like and retweet buttons of a tweet
+pub fn tweet_actions(ui: &mut Ui, tweet: &Tweet) -> Option<TweetAction> {
+    let mut action = None;
+    ui.horizontal(|ui| {
+        let liked = tweet.favorited == Some(true);
+        let label = format!("like {}", tweet.favorite_count);
+        if ui.selectable_label(liked, label).clicked() {
+            action = Some(if liked {
+                TweetAction::Unlike
+            } else {
+                TweetAction::Like
+            });
+        }
+        let retweeted = tweet.retweeted == Some(true);
+        let label = format!("retweet {}", tweet.retweet_count);
+        if ui.selectable_label(retweeted, label).clicked() {
+            action = Some(if retweeted {
+                TweetAction::Unretweet
+            } else {
+                TweetAction::Retweet
+            });
+        }
+    });
+    action
 }
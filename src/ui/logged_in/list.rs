@@ -1,28 +1,50 @@
+use super::{
+    column::{Column, ColumnAction},
+    detail,
+};
 use crate::background::Background;
 use egg_mode::tweet::Tweet;
 use egui::*;
 
-pub fn tweet_list<'a, ITER>(
-    tweets: ITER,
-    loading_more: &mut bool,
+/// Settings that are the same for every column
+pub struct ListSettings {
+    /// Older tweets are loaded automatically once the user scrolls to within this many tweets of
+    /// the bottom
+    pub prefetch_threshold: usize,
+    /// Hide the buttons that need a logged in user
+    pub read_only: bool,
+}
+
+/// Draw the tweets of a column, newest first.
+pub fn tweet_list(
+    column: &mut Column,
+    settings: &ListSettings,
     expanded_tweet: &Option<Tweet>,
     background: &mut Background,
     ui: &mut Ui,
-) -> Option<Tweet>
-where
-    ITER: Iterator<Item = &'a Tweet> + 'a + DoubleEndedIterator,
-{
-    let mut new_tweet = None;
+) -> Option<ColumnAction> {
+    let mut action = None;
+    let mut prefetch = false;
     ScrollArea::vertical().show(ui, |ui| {
         if ui
-            .add_enabled(!*loading_more, Button::new("Load newer"))
+            .add_enabled(!column.loading_more, Button::new("Load newer"))
             .clicked()
         {
-            background.load_newer();
-            *loading_more = true;
+            column.load_newer(background);
         }
-        for tweet in tweets.rev() {
+        let count = column.tweets.len();
+        for (idx, tweet) in column.tweets.iter().rev().enumerate() {
             ui.separator();
+            if let Some(gap) = column.gaps.iter().find(|g| g.since_id == tweet.id) {
+                if ui
+                    .add_enabled(!column.loading_more, Button::new("Load missing tweets"))
+                    .clicked()
+                {
+                    background.load_gap(column.timeline.clone(), *gap);
+                    column.loading_more = true;
+                }
+                ui.separator();
+            }
             ui.vertical(|ui| {
                 ui.horizontal(|ui| {
                     if let Some(user) = tweet.user.as_ref() {
@@ -32,11 +54,23 @@ where
                     }
                 });
                 ui.label(&tweet.text);
+                let mut button_clicked = false;
+                if !settings.read_only {
+                    let target = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                    if let Some(tweet_action) = detail::tweet_actions(ui, target) {
+                        action = Some(ColumnAction::Tweet(target.id, tweet_action));
+                        button_clicked = true;
+                    }
+                }
 
                 let mut rect = ui.min_rect();
                 rect.set_width(ui.max_rect().width());
                 let rect = rect.expand(5.0);
 
+                if count - idx <= settings.prefetch_threshold && ui.clip_rect().intersects(rect) {
+                    prefetch = true;
+                }
+
                 let is_hovered = ui.rect_contains_pointer(rect);
                 let is_active = expanded_tweet.as_ref().map(|t| t.id) == Some(tweet.id);
                 if is_hovered || is_active {
@@ -45,20 +79,24 @@ where
                 }
                 if is_hovered {
                     ui.ctx().output().cursor_icon = CursorIcon::PointingHand;
-                    if ui.ctx().input().pointer.any_click() {
-                        new_tweet = Some(tweet.clone());
+                    if ui.ctx().input().pointer.any_click() && !button_clicked {
+                        action = Some(ColumnAction::Select(Box::new(tweet.clone())));
                     }
                 }
             });
         }
         ui.separator();
-        if ui
-            .add_enabled(!*loading_more, Button::new("Load older"))
+        if column.reached_end {
+            ui.label("No older tweets");
+        } else if ui
+            .add_enabled(!column.loading_more, Button::new("Load older"))
             .clicked()
         {
-            background.load_older();
-            *loading_more = true;
+            column.load_older(background);
         }
     });
-    new_tweet
+    if prefetch && !column.loading_more && !column.load_failed && !column.reached_end {
+        column.load_older(background);
+    }
+    action
 }
@@ -1,37 +1,380 @@
+mod column;
+mod compose;
 mod detail;
 mod list;
+mod profile;
+mod split;
+mod thread;
 
+use self::{
+    column::{Column, ColumnAction},
+    compose::{Compose, ComposeAction},
+    list::ListSettings,
+};
 use super::utils::*;
-use crate::background::{twitter::User, Background, ToUI};
-use egg_mode::tweet::Tweet;
+use crate::background::{
+    twitter::User, unix_now, Background, Draft, ScheduledTweet, TimelineKind, ToUI, TweetAction,
+};
+use egg_mode::{list::List, tweet::Tweet, RateLimit};
 use egui::*;
 use egui_with_background::winit::VirtualKeyCode;
-use std::collections::VecDeque;
+use std::path::PathBuf;
 
 pub struct LoggedIn {
     user: User,
+    accounts: Vec<String>,
     error: Option<String>,
-    tweets: VecDeque<Tweet>,
+    /// The columns of the deck, guests start without any
+    columns: Vec<Column>,
+    /// The column that the sidebar and keyboard shortcuts act on
+    active_column: usize,
+    /// Open timelines from the sidebar in a new column instead of the active one
+    open_in_new_column: bool,
+    /// Screen name or search query to open a timeline for
+    browse_text: String,
+    saved_searches: Vec<String>,
+    /// Lists owned by the user, followed by the lists they are subscribed to
+    lists: Vec<List>,
     expanded_tweet: Option<Tweet>,
-    loading_more: bool,
+    /// The conversation of the expanded tweet, once the user asked for it
+    conversation: Option<thread::Conversation>,
+    compose: Option<Compose>,
+    /// Tweets the user started writing but didn't post, the most recently changed first
+    drafts: Vec<Draft>,
+    /// The ids of the drafts that are being posted, these can't be changed until that's done
+    posting: Vec<u64>,
+    /// Tweets that are posted later, the first to be posted first
+    scheduled: Vec<ScheduledTweet>,
+    /// A text field has keyboard focus, so key presses shouldn't trigger shortcuts
+    typing: bool,
+    list_settings: ListSettings,
+    /// The remaining requests of every endpoint we've called
+    rate_limits: Vec<(&'static str, RateLimit)>,
     new_version_available: Option<String>,
 }
 
 impl LoggedIn {
-    pub fn new(user: User, background: &mut Background) -> Box<Self> {
-        background.load_homepage();
-        Box::new(Self {
+    pub fn new(
+        user: User,
+        accounts: Vec<String>,
+        mut columns: Vec<TimelineKind>,
+        prefetch_threshold: usize,
+        background: &mut Background,
+    ) -> Box<Self> {
+        if columns.is_empty() && !user.is_guest() {
+            columns.push(TimelineKind::Home);
+        }
+        let list_settings = ListSettings {
+            prefetch_threshold,
+            read_only: user.is_guest(),
+        };
+        let mut result = Box::new(Self {
             user,
+            accounts,
             error: None,
-            tweets: VecDeque::new(),
+            columns: Vec::new(),
+            active_column: 0,
+            open_in_new_column: false,
+            browse_text: String::new(),
+            saved_searches: Vec::new(),
+            lists: Vec::new(),
             expanded_tweet: None,
-            loading_more: false,
+            conversation: None,
+            compose: None,
+            drafts: Vec::new(),
+            posting: Vec::new(),
+            scheduled: Vec::new(),
+            typing: false,
+            list_settings,
+            rate_limits: Vec::new(),
             new_version_available: None,
-        })
+        });
+        for timeline in columns {
+            if result.column_index(&timeline).is_none() {
+                result.columns.push(Column::new(background, timeline));
+            }
+        }
+        if !result.user.is_guest() {
+            background.load_lists();
+        }
+        result.save_columns(background);
+        result
+    }
+
+    fn column_index(&self, timeline: &TimelineKind) -> Option<usize> {
+        self.columns.iter().position(|c| c.timeline == *timeline)
+    }
+
+    fn active_timeline(&self) -> Option<&TimelineKind> {
+        self.columns.get(self.active_column).map(|c| &c.timeline)
+    }
+
+    /// Show the given timeline, returns the index of the column it is shown in. A timeline is
+    /// never shown in more than one column, as the background keeps track of a single range of
+    /// loaded tweets per timeline.
+    fn open_timeline(&mut self, background: &mut Background, timeline: TimelineKind) -> usize {
+        let idx = if let Some(idx) = self.column_index(&timeline) {
+            idx
+        } else if self.open_in_new_column || self.columns.is_empty() {
+            self.columns.push(Column::new(background, timeline));
+            self.columns.len() - 1
+        } else {
+            self.columns[self.active_column].open(background, timeline);
+            self.expanded_tweet = None;
+            self.active_column
+        };
+        self.active_column = idx;
+        self.save_columns(background);
+        idx
+    }
+
+    fn close_column(&mut self, background: &mut Background, idx: usize) {
+        self.columns.remove(idx);
+        if idx < self.active_column || self.active_column >= self.columns.len() {
+            self.active_column = self.active_column.saturating_sub(1);
+        }
+        self.save_columns(background);
+    }
+
+    /// Tell the background which timelines are shown, so it can poll them and remember them for
+    /// the next launch
+    fn save_columns(&self, background: &mut Background) {
+        let columns = self.columns.iter().map(|c| c.timeline.clone()).collect();
+        background.set_columns(columns);
+    }
+
+    fn handle_action(&mut self, background: &mut Background, action: detail::Action) {
+        match action {
+            detail::Action::OpenProfile(user) => {
+                let timeline = TimelineKind::User(user.screen_name.clone());
+                let idx = self.open_timeline(background, timeline);
+                // Show what we already know until the full profile is loaded
+                let column = &mut self.columns[idx];
+                if column.profile.is_none() {
+                    column.profile = Some(user);
+                }
+            }
+            detail::Action::Tweet(id, action) => self.tweet_action(background, id, action),
+            detail::Action::Reply(tweet) => {
+                let compose = Compose::reply(*tweet, self.user.id);
+                self.start_compose(background, compose)
+            }
+            detail::Action::Quote(tweet) => self.start_compose(background, Compose::quote(*tweet)),
+            detail::Action::ShowConversation(id) => {
+                if let Some(tweet) = &self.expanded_tweet {
+                    let tweet = thread::conversation_tweet(tweet).clone();
+                    background.load_conversation(tweet);
+                    self.conversation = Some(thread::Conversation::loading(id));
+                }
+            }
+        }
+    }
+
+    fn handle_column_action(
+        &mut self,
+        background: &mut Background,
+        idx: usize,
+        action: ColumnAction,
+    ) {
+        match action {
+            ColumnAction::Activate => self.active_column = idx,
+            ColumnAction::Close => self.close_column(background, idx),
+            ColumnAction::Open(timeline) => {
+                self.active_column = idx;
+                self.open_timeline(background, timeline);
+            }
+            ColumnAction::Tweet(id, action) => self.tweet_action(background, id, action),
+            ColumnAction::Select(tweet) => {
+                self.active_column = idx;
+                self.set_expanded_tweet(background, *tweet);
+            }
+        }
+    }
+
+    /// Like or retweet a tweet, this is shown right away and undone if it fails
+    fn tweet_action(&mut self, background: &mut Background, id: u64, action: TweetAction) {
+        self.apply_tweet_action(id, action);
+        background.tweet_action(id, action);
+    }
+
+    /// Update every copy of the tweet with the given id that we're showing
+    fn apply_tweet_action(&mut self, id: u64, action: TweetAction) {
+        let columns = self.columns.iter_mut();
+        let tweets = columns.flat_map(|c| c.tweets.iter_mut().chain(c.new_tweets.iter_mut()));
+        let conversation = self
+            .conversation
+            .iter_mut()
+            .flat_map(|c| c.ancestors.iter_mut().chain(c.replies.iter_mut()));
+        for tweet in tweets
+            .chain(conversation)
+            .chain(self.expanded_tweet.iter_mut())
+        {
+            apply_to_tweet(tweet, id, action);
+        }
+    }
+
+    fn open_compose(&mut self, background: &mut Background) {
+        if self.compose.is_none() {
+            self.start_compose(background, Compose::default());
+        }
+    }
+
+    /// Replace the composer. Tweets that are being posted from it are posted anyway.
+    fn start_compose(&mut self, background: &mut Background, mut compose: Compose) {
+        if self.user.is_guest() {
+            return;
+        }
+        self.close_compose(background);
+        compose.posting = self.posting.contains(&compose.draft.id);
+        self.compose = Some(compose);
+    }
+
+    /// Close the composer, saving what changed since it was last saved
+    fn close_compose(&mut self, background: &mut Background) {
+        if let Some(draft) = self.compose.take().and_then(|mut c| c.flush_changes()) {
+            self.save_draft(background, draft);
+        }
+    }
+
+    fn handle_compose_action(&mut self, background: &mut Background, action: ComposeAction) {
+        match action {
+            ComposeAction::Post => {
+                // The background deletes the stored draft once it's posted, so it has to be
+                // saved first
+                if let Some(draft) = self.compose.as_mut().and_then(Compose::flush_changes) {
+                    self.save_draft(background, draft);
+                }
+                if let Some(compose) = &mut self.compose {
+                    let draft = &compose.draft;
+                    if draft.thread.is_empty() {
+                        background.post_tweet(draft.id, compose.new_tweet());
+                    } else {
+                        background.post_thread(draft.id, draft.thread_tweets());
+                    }
+                    self.posting.push(draft.id);
+                    compose.posting = true;
+                    compose.error = None;
+                }
+            }
+            ComposeAction::Schedule(post_at) => {
+                if let Some(compose) = self.compose.take() {
+                    self.delete_draft(background, compose.draft.id);
+                    background.schedule_tweet(ScheduledTweet {
+                        post_at,
+                        error: None,
+                        draft: compose.draft,
+                    });
+                }
+            }
+            ComposeAction::Close => self.close_compose(background),
+            ComposeAction::Discard => {
+                if let Some(compose) = self.compose.take() {
+                    self.delete_draft(background, compose.draft.id);
+                }
+            }
+        }
+    }
+
+    /// Remember the draft, drafts without any content are deleted instead
+    fn save_draft(&mut self, background: &mut Background, draft: Draft) {
+        if draft.is_empty() {
+            self.delete_draft(background, draft.id);
+            return;
+        }
+        self.drafts.retain(|d| d.id != draft.id);
+        self.drafts.insert(0, draft.clone());
+        background.save_draft(draft);
+    }
+
+    fn delete_draft(&mut self, background: &mut Background, id: u64) {
+        self.drafts.retain(|d| d.id != id);
+        background.delete_draft(id);
+    }
+
+    /// Take a tweet off the schedule and open it in the composer. It's kept as a draft until it's
+    /// scheduled again.
+    fn edit_scheduled(&mut self, background: &mut Background, tweet: ScheduledTweet) {
+        if self.user.is_guest() {
+            return;
+        }
+        background.cancel_scheduled_tweet(tweet.id());
+        self.scheduled.retain(|t| t.id() != tweet.id());
+        self.save_draft(background, tweet.draft.clone());
+        self.close_compose(background);
+        self.compose = Some(Compose::scheduled(tweet.draft, tweet.post_at));
+    }
+
+    /// Continue writing a draft, unless it is already open
+    fn open_draft(&mut self, background: &mut Background, draft: Draft) {
+        if matches!(&self.compose, Some(c) if c.draft.id == draft.id) {
+            return;
+        }
+        self.start_compose(background, Compose::from_draft(draft));
+    }
+
+    /// The draft with the given id was posted, the background already deleted it
+    fn draft_posted(&mut self, id: u64) {
+        self.posting.retain(|p| *p != id);
+        self.drafts.retain(|d| d.id != id);
+        if matches!(&self.compose, Some(c) if c.draft.id == id) {
+            self.compose = None;
+        }
+    }
+
+    /// The composer, if it's showing the draft with the given id
+    fn compose_of(&mut self, id: u64) -> Option<&mut Compose> {
+        self.compose.as_mut().filter(|c| c.draft.id == id)
+    }
+
+    /// Attach files that were dropped onto the window, opening the composer if needed
+    fn attach_files(&mut self, background: &mut Background, paths: Vec<PathBuf>) {
+        self.open_compose(background);
+        if let Some(compose) = &mut self.compose {
+            if !compose.posting {
+                for path in paths {
+                    compose.attach(path);
+                }
+            }
+        }
+    }
+
+    /// Show a tweet the user posted in the timelines it belongs in, without waiting for them to
+    /// be reloaded
+    fn insert_own_tweet(&mut self, tweet: Tweet) {
+        for column in &mut self.columns {
+            let belongs = match &column.timeline {
+                TimelineKind::Home => true,
+                TimelineKind::User(name) => name.eq_ignore_ascii_case(&self.user.name),
+                _ => false,
+            };
+            if belongs {
+                column.new_tweets.retain(|t| t.id != tweet.id);
+                let (loading_more, load_failed) = (column.loading_more, column.load_failed);
+                column.add_tweets(vec![tweet.clone()]);
+                column.loading_more = loading_more;
+                column.load_failed = load_failed;
+            }
+        }
+    }
+
+    fn load_newer(&mut self, background: &mut Background) {
+        if let Some(column) = self.columns.get_mut(self.active_column) {
+            column.load_newer(background);
+        }
+    }
+
+    fn load_older(&mut self, background: &mut Background) {
+        if let Some(column) = self.columns.get_mut(self.active_column) {
+            column.load_older(background);
+        }
     }
 
     fn set_expanded_tweet(&mut self, background: &mut Background, tweet: Tweet) {
-        background.set_latest_tweet(tweet.id);
+        if let Some(timeline) = self.active_timeline() {
+            if !self.user.is_guest() {
+                background.set_latest_tweet(timeline.clone(), tweet.id);
+            }
+        }
         self.expanded_tweet = Some(tweet);
     }
 
@@ -39,24 +382,126 @@ impl LoggedIn {
         match msg {
             ToUI::Error { error } => {
                 self.error = Some(error);
+                for column in &mut self.columns {
+                    column.load_failed |= column.loading_more;
+                    column.loading_more = false;
+                }
             }
             ToUI::Loading => {}
-            ToUI::Tweets { tweets, latest } => {
-                for tweet in tweets {
-                    match self.tweets.binary_search_by_key(&tweet.id, |t| t.id) {
-                        Ok(idx) => self.tweets[idx] = tweet,
-                        Err(idx) => self.tweets.insert(idx, tweet),
-                    }
+            ToUI::Tweets {
+                timeline,
+                tweets,
+                latest,
+                polled,
+                gaps,
+                reached_end,
+            } => {
+                let idx = match self.column_index(&timeline) {
+                    Some(idx) => idx,
+                    // The column was closed or switched to a different timeline while these were
+                    // loading
+                    None => return,
+                };
+                self.columns[idx].gaps = gaps;
+                self.columns[idx].reached_end = reached_end;
+                if polled {
+                    self.columns[idx].add_polled_tweets(tweets);
+                    return;
                 }
-                self.loading_more = false;
-                if self.expanded_tweet.is_none() {
+                self.columns[idx].add_tweets(tweets);
+                if idx == self.active_column && self.expanded_tweet.is_none() {
                     if let Some(latest) = latest {
-                        if let Some(tweet) = self.tweets.iter().find(|t| t.id == latest).cloned() {
+                        let tweets = &self.columns[idx].tweets;
+                        if let Some(tweet) = tweets.iter().find(|t| t.id == latest).cloned() {
                             self.set_expanded_tweet(background, tweet);
                         }
                     }
                 }
             }
+            ToUI::SavedSearches { queries } => self.saved_searches = queries,
+            ToUI::Lists { owned, subscribed } => {
+                self.lists = owned;
+                for list in subscribed {
+                    if !self.lists.iter().any(|l| l.id == list.id) {
+                        self.lists.push(list);
+                    }
+                }
+            }
+            ToUI::TweetPosted { draft, tweet } => {
+                self.draft_posted(draft);
+                self.insert_own_tweet(*tweet);
+            }
+            ToUI::ThreadTweetPosted { draft, tweet } => {
+                // Keep the copies of the draft in line with the one the background stored
+                for stored in self.drafts.iter_mut().filter(|d| d.id == draft) {
+                    stored.posted.push(tweet.id);
+                }
+                if let Some(compose) = self.compose_of(draft) {
+                    compose.thread_tweet_posted(tweet.id);
+                }
+                self.insert_own_tweet(*tweet);
+            }
+            ToUI::ThreadPosted { draft } => self.draft_posted(draft),
+            ToUI::Drafts { drafts } => self.drafts = drafts,
+            ToUI::ScheduledTweets { tweets } => self.scheduled = tweets,
+            ToUI::ScheduledTweetPosted { tweet } => self.insert_own_tweet(*tweet),
+            ToUI::Preview { path, preview } => {
+                if let Some(compose) = &mut self.compose {
+                    compose.set_preview(path, preview);
+                }
+            }
+            ToUI::UploadProgress {
+                draft,
+                index,
+                stage,
+            } => {
+                if let Some(compose) = self.compose_of(draft) {
+                    compose.upload = Some((index, stage));
+                }
+            }
+            ToUI::TweetActionFailed { id, action, error } => {
+                self.apply_tweet_action(id, action.undo());
+                self.error = Some(format!("Could not {} the tweet: {}", action.verb(), error));
+            }
+            ToUI::PostFailed { draft, error } => {
+                self.posting.retain(|p| *p != draft);
+                match self.compose_of(draft) {
+                    Some(compose) => {
+                        compose.posting = false;
+                        compose.upload = None;
+                        compose.error = Some(error);
+                    }
+                    None => {
+                        self.error = Some(format!(
+                            "Could not post a tweet, it's kept as a draft: {}",
+                            error
+                        ))
+                    }
+                }
+            }
+            ToUI::Conversation {
+                id,
+                ancestors,
+                replies,
+            } => {
+                if let Some(conversation) = &mut self.conversation {
+                    if conversation.id == id {
+                        conversation.ancestors = ancestors;
+                        conversation.replies = replies;
+                        conversation.loading = false;
+                    }
+                }
+            }
+            ToUI::Profile { user } => {
+                for column in &mut self.columns {
+                    if let Some(screen_name) = column.timeline.screen_name() {
+                        if screen_name.eq_ignore_ascii_case(&user.screen_name) {
+                            column.profile = Some(user.clone());
+                        }
+                    }
+                }
+            }
+            ToUI::RateLimits { limits } => self.rate_limits = limits,
             ToUI::NewVersionAvailable { url } => {
                 self.new_version_available = Some(url);
             }
@@ -65,80 +510,359 @@ impl LoggedIn {
     }
 
     pub fn draw(&mut self, ctx: &mut crate::Context) {
-        SidePanel::left("tweet_list").show(ctx.ctx, |ui| {
+        let mut open_compose = false;
+        SidePanel::left("navigation").show(ctx.ctx, |ui| {
             ui.horizontal(|ui| {
+                if self.user.is_guest() {
+                    ui.add(Label::new(RichText::new("Browsing as guest").strong()));
+                    ui.separator();
+                    if ui.add(ClickableLink::new("log in")).clicked() {
+                        ctx.background.leave_guest();
+                    }
+                    return;
+                }
                 ui.add(Label::new(RichText::new(&self.user.name).strong()));
+                ComboBox::from_id_source("account_switcher")
+                    .selected_text("switch")
+                    .show_ui(ui, |ui| {
+                        for account in &self.accounts {
+                            if ui
+                                .selectable_label(*account == self.user.name, account)
+                                .clicked()
+                                && *account != self.user.name
+                            {
+                                ctx.background.switch_account(account.clone());
+                            }
+                        }
+                        ui.separator();
+                        if ui.selectable_label(false, "Add account").clicked() {
+                            ctx.background.add_account();
+                        }
+                    });
                 ui.separator();
                 if ui.add(ClickableLink::new("log out")).clicked() {
                     ctx.background.logout();
                 }
+                ui.separator();
+                if ui.button("New tweet").on_hover_text("N").clicked() {
+                    open_compose = true;
+                }
                 if let Some(url) = self.new_version_available.as_ref() {
                     ui.separator();
                     ui.hyperlink_to("New version available", url);
                 }
             });
             ui.separator();
-            if let Some(tweet) = list::tweet_list(
-                self.tweets.iter(),
-                &mut self.loading_more,
-                &self.expanded_tweet,
-                ctx.background,
-                ui,
-            ) {
-                self.set_expanded_tweet(ctx.background, tweet);
+            if !self.user.is_guest() {
+                ui.horizontal(|ui| {
+                    for (timeline, title) in [
+                        (TimelineKind::Home, "Home"),
+                        (TimelineKind::Mentions, "Mentions"),
+                        (TimelineKind::Likes(self.user.name.clone()), "Likes"),
+                    ] {
+                        let selected = self.active_timeline() == Some(&timeline);
+                        if ui.selectable_label(selected, title).clicked() && !selected {
+                            self.open_timeline(ctx.background, timeline);
+                        }
+                    }
+                    let active_timeline = self.active_timeline();
+                    let current_list = self
+                        .lists
+                        .iter()
+                        .find(|l| active_timeline == Some(&TimelineKind::List(l.id)));
+                    let mut selected_list = None;
+                    ComboBox::from_id_source("list_picker")
+                        .selected_text(current_list.map_or("Lists", |l| l.name.as_str()))
+                        .show_ui(ui, |ui| {
+                            if self.lists.is_empty() {
+                                ui.label("No lists");
+                            }
+                            for list in &self.lists {
+                                let selected = current_list.map(|l| l.id) == Some(list.id);
+                                if ui.selectable_label(selected, &list.full_name).clicked()
+                                    && !selected
+                                {
+                                    selected_list = Some(list.id);
+                                }
+                            }
+                        });
+                    if let Some(id) = selected_list {
+                        self.open_timeline(ctx.background, TimelineKind::List(id));
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.add(TextEdit::singleline(&mut self.browse_text).desired_width(120.));
+                let browse_text = self.browse_text.trim().to_owned();
+                if ui
+                    .add_enabled(!browse_text.is_empty(), Button::new("User"))
+                    .clicked()
+                {
+                    let name = browse_text.trim_start_matches('@').to_owned();
+                    self.open_timeline(ctx.background, TimelineKind::User(name));
+                }
+                if ui
+                    .add_enabled(!browse_text.is_empty(), Button::new("Search"))
+                    .clicked()
+                {
+                    self.open_timeline(ctx.background, TimelineKind::Search(browse_text));
+                }
+            });
+            if !self.saved_searches.is_empty() {
+                CollapsingHeader::new("Saved searches")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        let mut open = None;
+                        for query in &self.saved_searches {
+                            ui.horizontal(|ui| {
+                                let timeline = TimelineKind::Search(query.clone());
+                                let selected = self.active_timeline() == Some(&timeline);
+                                if ui.selectable_label(selected, query).clicked() && !selected {
+                                    open = Some(timeline);
+                                }
+                                if ui.small_button("x").on_hover_text("Remove").clicked() {
+                                    ctx.background.remove_saved_search(query.clone());
+                                }
+                            });
+                        }
+                        if let Some(timeline) = open {
+                            self.open_timeline(ctx.background, timeline);
+                        }
+                    });
+            }
+            if !self.drafts.is_empty() {
+                let mut open = None;
+                let mut discard = None;
+                CollapsingHeader::new(format!("Drafts ({})", self.drafts.len())).show(ui, |ui| {
+                    for draft in &self.drafts {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add(ClickableLink::new(compose::summary(draft)))
+                                .clicked()
+                            {
+                                open = Some(draft.clone());
+                            }
+                            if self.posting.contains(&draft.id) {
+                                ui.label(RichText::new("posting").weak());
+                            } else if ui.small_button("x").on_hover_text("Discard").clicked() {
+                                discard = Some(draft.id);
+                            }
+                        });
+                    }
+                });
+                if let Some(draft) = open {
+                    self.open_draft(ctx.background, draft);
+                }
+                if let Some(id) = discard {
+                    if self.compose_of(id).is_some() {
+                        self.compose = None;
+                    }
+                    self.delete_draft(ctx.background, id);
+                }
+            }
+            if !self.scheduled.is_empty() {
+                let mut edit = None;
+                let mut cancel = None;
+                let title = format!("Scheduled ({})", self.scheduled.len());
+                CollapsingHeader::new(title).show(ui, |ui| {
+                    for tweet in &self.scheduled {
+                        ui.horizontal(|ui| {
+                            ui.label(compose::format_time(tweet.post_at));
+                            if ui
+                                .add(ClickableLink::new(compose::summary(&tweet.draft)))
+                                .on_hover_text("Edit")
+                                .clicked()
+                            {
+                                edit = Some(tweet.clone());
+                            }
+                            if ui.small_button("x").on_hover_text("Cancel").clicked() {
+                                cancel = Some(tweet.id());
+                            }
+                        });
+                        if let Some(error) = &tweet.error {
+                            ui.colored_label(Color32::RED, format!("Not posted: {}", error));
+                        }
+                    }
+                });
+                if let Some(tweet) = edit {
+                    self.edit_scheduled(ctx.background, tweet);
+                }
+                if let Some(id) = cancel {
+                    self.scheduled.retain(|t| t.id() != id);
+                    ctx.background.cancel_scheduled_tweet(id);
+                }
+            }
+            if let Some(TimelineKind::Search(query)) = self.active_timeline() {
+                if !self.saved_searches.contains(query)
+                    && ui.add(ClickableLink::new("Save this search")).clicked()
+                {
+                    ctx.background.save_search(query.clone());
+                }
+            }
+            ui.separator();
+            self.draw_rate_limits(ui);
+            ui.checkbox(&mut self.open_in_new_column, "Open in a new column");
+            if self.columns.is_empty() {
+                ui.label("Enter a screen name or a search query to browse tweets");
             }
         });
+        let mut column_action = None;
+        for (idx, column) in self.columns.iter_mut().enumerate() {
+            let title = column.title(&self.lists);
+            let active = idx == self.active_column;
+            let settings = &self.list_settings;
+            let expanded_tweet = &self.expanded_tweet;
+            SidePanel::left(("column", column.timeline.clone())).show(ctx.ctx, |ui| {
+                let action = column.draw(ctx, ui, title, active, settings, expanded_tweet);
+                if let Some(action) = action {
+                    column_action = Some((idx, action));
+                }
+            });
+        }
+        if let Some((idx, action)) = column_action {
+            self.handle_column_action(ctx.background, idx, action);
+        }
         if let Some(error) = &self.error {
             TopBottomPanel::top("tweet_error").show(ctx.ctx, |ui| {
                 ui.label(error);
             });
         }
+        let mut action = None;
         if let Some(tweet) = &self.expanded_tweet {
             CentralPanel::default().show(ctx.ctx, |ui| {
                 ScrollArea::both().show(ui, |ui| {
-                    detail::draw_tweet(ctx, ui, tweet);
+                    let conversation = self.conversation.as_ref();
+                    let read_only = self.user.is_guest();
+                    action = thread::draw(ctx, ui, tweet, conversation, read_only);
                 });
             });
         }
+        if let Some(action) = action {
+            self.handle_action(ctx.background, action);
+        }
+        if open_compose {
+            self.open_compose(ctx.background);
+        }
+        let dropped: Vec<PathBuf> = ctx
+            .ctx
+            .input()
+            .raw
+            .dropped_files
+            .iter()
+            .filter_map(|file| file.path.clone())
+            .collect();
+        if !dropped.is_empty() {
+            self.attach_files(ctx.background, dropped);
+        }
+        let compose_action = self.compose.as_mut().and_then(|c| c.draw(ctx));
+        if let Some(compose) = &mut self.compose {
+            match compose.take_changes() {
+                Some(draft) => self.save_draft(ctx.background, draft),
+                // Draw again once the draft should be saved, even if nothing else happens
+                None if compose.has_changes() => ctx.ctx.request_repaint(),
+                None => {}
+            }
+        }
+        if let Some(action) = compose_action {
+            self.handle_compose_action(ctx.background, action);
+        }
+        self.typing = ctx.ctx.memory().focus().is_some();
     }
 
-    pub fn key_pressed(&mut self, background: &mut Background, keycode: VirtualKeyCode) {
+    fn draw_rate_limits(&self, ui: &mut Ui) {
+        if self.rate_limits.is_empty() {
+            return;
+        }
+        let now = unix_now() as i64;
+        let resets_in = |limit: &RateLimit| (limit.reset as i64 - now).max(0);
+        let exhausted = self
+            .rate_limits
+            .iter()
+            .filter(|(_, l)| l.remaining == 0 && resets_in(l) > 0)
+            .map(|(_, l)| resets_in(l))
+            .max();
+        if let Some(wait) = exhausted {
+            ui.colored_label(
+                Color32::YELLOW,
+                format!("Rate limited, waiting {}:{:02}", wait / 60, wait % 60),
+            );
+        }
+        CollapsingHeader::new("Rate limits").show(ui, |ui| {
+            Grid::new("rate_limits").striped(true).show(ui, |ui| {
+                for (endpoint, limit) in &self.rate_limits {
+                    ui.label(*endpoint);
+                    ui.label(format!("{}/{}", limit.remaining, limit.limit));
+                    let wait = resets_in(limit);
+                    ui.label(format!("resets in {}:{:02}", wait / 60, wait % 60));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// Handle a keyboard shortcut, returns `false` if the key wasn't used
+    pub fn key_pressed(&mut self, background: &mut Background, keycode: VirtualKeyCode) -> bool {
+        if keycode == VirtualKeyCode::Escape && self.compose.is_some() {
+            self.close_compose(background);
+            return true;
+        }
+        if self.typing {
+            return false;
+        }
+        if keycode == VirtualKeyCode::N {
+            self.open_compose(background);
+            return true;
+        }
+        let tweets = match self.columns.get(self.active_column) {
+            Some(column) => &column.tweets,
+            None => return false,
+        };
         match keycode {
             VirtualKeyCode::Up => {
                 if let Some(tweet) = &self.expanded_tweet {
-                    if let Some(idx) = self.tweets.iter().position(|t| t.id == tweet.id) {
-                        if let Some(tweet) = self.tweets.get(idx + 1).cloned() {
+                    if let Some(idx) = tweets.iter().position(|t| t.id == tweet.id) {
+                        if let Some(tweet) = tweets.get(idx + 1).cloned() {
                             self.set_expanded_tweet(background, tweet);
                         } else {
-                            background.load_newer();
-                            self.loading_more = true;
+                            self.load_newer(background);
                         }
                     }
                 }
             }
             VirtualKeyCode::Down => {
                 if let Some(tweet) = &self.expanded_tweet {
-                    if let Some(idx) = self.tweets.iter().position(|t| t.id == tweet.id) {
+                    if let Some(idx) = tweets.iter().position(|t| t.id == tweet.id) {
                         if idx > 0 {
-                            let tweet = self.tweets.get(idx - 1).unwrap().clone();
+                            let tweet = tweets.get(idx - 1).unwrap().clone();
                             self.set_expanded_tweet(background, tweet);
                         } else {
-                            background.load_older();
-                            self.loading_more = true;
+                            self.load_older(background);
                         }
                     }
                 }
             }
             VirtualKeyCode::Home => {
-                if let Some(last) = self.tweets.back().cloned() {
+                if let Some(last) = tweets.back().cloned() {
                     self.set_expanded_tweet(background, last);
                 }
             }
-            VirtualKeyCode::F5 => {
-                background.load_newer();
-                self.loading_more = true;
-            }
-            _ => {}
+            VirtualKeyCode::F5 => self.load_newer(background),
+            _ => return false,
         }
+        true
+    }
+}
+
+/// Apply the action to the tweet if it has the given id, or to the retweeted or quoted tweet
+/// inside of it.
+fn apply_to_tweet(tweet: &mut Tweet, id: u64, action: TweetAction) {
+    if tweet.id == id {
+        action.apply(tweet);
+    }
+    if let Some(retweeted) = tweet.retweeted_status.as_deref_mut() {
+        apply_to_tweet(retweeted, id, action);
+    }
+    if let Some(quoted) = tweet.quoted_status.as_deref_mut() {
+        apply_to_tweet(quoted, id, action);
     }
 }
@@ -0,0 +1,39 @@
+use crate::ui::utils::Image;
+use egg_mode::user::TwitterUser;
+use egui::*;
+
+pub fn draw_profile(ctx: &mut crate::Context, ui: &mut Ui, user: &TwitterUser) {
+    if let Some(banner) = &user.profile_banner_url {
+        // Twitter serves banners in a couple of fixed sizes
+        ui.add(Image::https(
+            ctx.background,
+            format!("{}/600x200", banner),
+            (300., 100.),
+        ));
+    }
+    ui.horizontal(|ui| {
+        ui.add(Image::https(
+            ctx.background,
+            &user.profile_image_url_https,
+            (48., 48.),
+        ));
+        ui.vertical(|ui| {
+            ui.label(RichText::new(&user.name).strong());
+            ui.hyperlink_to(
+                format!("@{}", user.screen_name),
+                format!("https://twitter.com/{}", user.screen_name),
+            );
+        });
+    });
+    if let Some(description) = user.description.as_ref() {
+        ui.label(description);
+    }
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(user.friends_count.to_string()).strong());
+        ui.label("following");
+        ui.separator();
+        ui.label(RichText::new(user.followers_count.to_string()).strong());
+        ui.label("followers");
+    });
+    ui.label(format!("Joined {}", user.created_at.format("%B %Y")));
+}
@@ -0,0 +1,251 @@
+use super::compose::{weighted_length, MAX_WEIGHTED_LENGTH};
+
+/// A change the user made to the split points of a thread
+#[derive(Clone, Copy)]
+pub enum ThreadEdit {
+    /// Move the first sentence of the tweet at this index to the end of the one before it
+    MoveUp(usize),
+    /// Move the last sentence of the tweet at this index to the start of the one after it
+    MoveDown(usize),
+    /// Merge the tweet at this index with the one after it
+    Merge(usize),
+    /// Split the tweet at this index again, because it's too long
+    Split(usize),
+}
+
+impl ThreadEdit {
+    /// Whether the thread has as many tweets after this edit as it has now
+    pub fn keeps_count(self, parts: &[String]) -> bool {
+        let mut edited = parts.to_vec();
+        self.apply(&mut edited);
+        edited.len() == parts.len()
+    }
+
+    pub fn apply(self, parts: &mut Vec<String>) {
+        match self {
+            Self::MoveUp(index) if index > 0 && index < parts.len() => {
+                let text = std::mem::take(&mut parts[index]);
+                let sentences = sentences(&text);
+                join(&mut parts[index - 1], sentences[0]);
+                parts[index] = sentences[1..].concat().trim_start().to_owned();
+            }
+            Self::MoveDown(index) if index + 1 < parts.len() => {
+                let text = std::mem::take(&mut parts[index]);
+                let mut sentences = sentences(&text);
+                let last = sentences.pop().unwrap_or_default();
+                let mut next = last.trim_start().to_owned();
+                join(&mut next, &parts[index + 1]);
+                parts[index + 1] = next;
+                parts[index] = sentences.concat().trim_start().to_owned();
+            }
+            Self::Merge(index) if index + 1 < parts.len() => {
+                let next = parts.remove(index + 1);
+                join(&mut parts[index], &next);
+            }
+            Self::Split(index) if index < parts.len() => {
+                let split = split_thread(&parts[index]);
+                parts.splice(index..=index, split);
+            }
+            _ => {}
+        }
+        parts.retain(|p| !p.trim().is_empty());
+    }
+}
+
+/// Split text into tweets that fit within the weighted limit once they are numbered. The text is
+/// split between sentences where possible, and between words otherwise. Every tweet keeps the
+/// whitespace it was split at, so `join_thread` can put the text back together.
+pub fn split_thread(text: &str) -> Vec<String> {
+    // Leave room for the " 1/3" that is added to every tweet, with more digits as needed
+    let mut digits = 1;
+    loop {
+        let limit = MAX_WEIGHTED_LENGTH - (2 * digits + 2);
+        let mut parts = Vec::new();
+        let mut current = String::new();
+        pack(&mut parts, &mut current, text, Level::Sentences, limit);
+        if !current.trim().is_empty() {
+            parts.push(current.trim_start().to_owned());
+        }
+        if parts.len() < 10usize.pow(digits as u32) {
+            return parts;
+        }
+        digits += 1;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Level {
+    Sentences,
+    Words,
+    Characters,
+}
+
+/// Add the pieces of `text` to `current` while they fit, starting a new tweet when they don't.
+/// Pieces that don't fit in a tweet by themselves are split further.
+fn pack(parts: &mut Vec<String>, current: &mut String, text: &str, level: Level, limit: usize) {
+    let pieces = match level {
+        Level::Sentences => sentences(text),
+        Level::Words => text.split_inclusive(char::is_whitespace).collect(),
+        Level::Characters => text
+            .char_indices()
+            .map(|(i, c)| &text[i..i + c.len_utf8()])
+            .collect(),
+    };
+    let fits = |text: &str| weighted_length(text.trim()) <= limit;
+    for piece in pieces {
+        let candidate = format!("{}{}", current, piece);
+        if fits(&candidate) {
+            *current = candidate;
+            continue;
+        }
+        if !current.trim().is_empty() {
+            parts.push(current.trim_start().to_owned());
+        }
+        current.clear();
+        match level {
+            _ if fits(piece) => current.push_str(piece.trim_start()),
+            Level::Sentences => pack(parts, current, piece, Level::Words, limit),
+            Level::Words | Level::Characters => {
+                pack(parts, current, piece, Level::Characters, limit)
+            }
+        }
+    }
+}
+
+/// Join the tweets of a thread back into a single text, with the whitespace they were split at
+pub fn join_thread(parts: &[String]) -> String {
+    let mut text = String::new();
+    for part in parts {
+        join(&mut text, part);
+    }
+    text.trim_end().to_owned()
+}
+
+/// Split text after the end of every sentence and line, keeping the whitespace after it so the
+/// pieces add up to the original text. Empty lines belong to the sentence before them.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    let mut sentence_ended = false;
+    // A sentence or line ended, the next piece starts at the next character that isn't whitespace
+    let mut at_break = false;
+    for (i, c) in text.char_indices() {
+        if at_break && !c.is_whitespace() {
+            result.push(&text[start..i]);
+            start = i;
+            at_break = false;
+        }
+        if c == '\n' || (sentence_ended && c.is_whitespace()) {
+            at_break = true;
+            sentence_ended = false;
+        } else if matches!(c, '.' | '!' | '?' | '…') {
+            sentence_ended = true;
+        } else if !matches!(c, '"' | '\'' | ')' | '”' | '’') {
+            // Closing quotes and brackets can follow the end of a sentence
+            sentence_ended = false;
+        }
+    }
+    if start < text.len() {
+        result.push(&text[start..]);
+    }
+    if result.is_empty() {
+        result.push("");
+    }
+    result
+}
+
+/// Append `text` to `part`, after the whitespace `part` ends with or a space if it doesn't end with
+/// any
+fn join(part: &mut String, text: &str) {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return;
+    }
+    if part.trim().is_empty() {
+        part.clear();
+    } else if !part.ends_with(char::is_whitespace) {
+        part.push(' ');
+    }
+    part.push_str(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::numbered;
+
+    fn fits(parts: &[String]) -> bool {
+        let count = parts.len();
+        let mut tweets = parts.iter().enumerate().map(|(i, p)| numbered(p, i, count));
+        tweets.all(|t| weighted_length(&t) <= MAX_WEIGHTED_LENGTH)
+    }
+
+    fn thread(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn parts_fit_once_numbered() {
+        let text = "This is one of the many sentences of a long text. ".repeat(40);
+        let parts = split_thread(&text);
+        assert!(parts.len() > 1);
+        assert!(fits(&parts));
+        // Split between sentences
+        assert!(parts.iter().all(|p| p.trim_end().ends_with('.')));
+    }
+
+    #[test]
+    fn long_words_are_split_by_characters() {
+        let word = "a".repeat(600);
+        let parts = split_thread(&format!("Short. {} end", word));
+        assert!(fits(&parts));
+        assert_eq!(parts.concat().matches('a').count(), 600);
+        assert!(parts.iter().all(|p| weighted_length(p.trim()) > 0));
+    }
+
+    #[test]
+    fn two_digit_numbers_leave_less_room() {
+        // Ten parts with room for " 1/9", so it has to be split again with room for " 10/10"
+        let text = "a".repeat(2700);
+        let parts = split_thread(&text);
+        assert_eq!(parts.len(), 10);
+        assert_eq!(parts[0].len(), MAX_WEIGHTED_LENGTH - 6);
+        assert!(fits(&parts));
+        assert_eq!(parts.concat(), text);
+    }
+
+    #[test]
+    fn moving_the_only_sentence_removes_the_tweet() {
+        let mut parts = thread(&["One. Two. ", "Three. ", "Four. Five."]);
+        ThreadEdit::MoveUp(1).apply(&mut parts);
+        assert_eq!(parts, thread(&["One. Two. Three. ", "Four. Five."]));
+
+        let mut parts = thread(&["One. Two. ", "Three. ", "Four. Five."]);
+        ThreadEdit::MoveDown(1).apply(&mut parts);
+        assert_eq!(parts, thread(&["One. Two. ", "Three. Four. Five."]));
+    }
+
+    #[test]
+    fn edits_that_change_the_count() {
+        let parts = thread(&["One. Two. ", "Three. ", "Four. Five."]);
+        assert!(ThreadEdit::MoveUp(2).keeps_count(&parts));
+        assert!(ThreadEdit::MoveDown(0).keeps_count(&parts));
+        assert!(!ThreadEdit::MoveUp(1).keeps_count(&parts));
+        assert!(!ThreadEdit::Merge(0).keeps_count(&parts));
+    }
+
+    #[test]
+    fn joining_keeps_the_separators() {
+        let paragraph = "A sentence that is part of a paragraph. ".repeat(5);
+        let text = format!("{0}\n\n{0}\n{0}", paragraph.trim_end());
+        let mut parts = split_thread(&text);
+        assert!(parts.len() > 1);
+        assert_eq!(join_thread(&parts), text);
+
+        ThreadEdit::MoveDown(0).apply(&mut parts);
+        ThreadEdit::MoveUp(1).apply(&mut parts);
+        assert_eq!(join_thread(&parts), text);
+        ThreadEdit::Merge(0).apply(&mut parts);
+        assert_eq!(join_thread(&parts), text);
+    }
+}
@@ -0,0 +1,73 @@
+use super::detail::{self, Action};
+use crate::ui::utils::ClickableLink;
+use egg_mode::tweet::Tweet;
+use egui::*;
+
+/// The tweets around a tweet that is shown in the detail panel
+pub struct Conversation {
+    /// The tweet this conversation was loaded for
+    pub id: u64,
+    /// The tweets that the tweet replies to, starting at the root of the conversation
+    pub ancestors: Vec<Tweet>,
+    pub replies: Vec<Tweet>,
+    pub loading: bool,
+}
+
+impl Conversation {
+    pub fn loading(id: u64) -> Self {
+        Self {
+            id,
+            ancestors: Vec::new(),
+            replies: Vec::new(),
+            loading: true,
+        }
+    }
+}
+
+/// The tweet that a conversation is loaded for, retweets belong to the conversation of the
+/// original tweet.
+pub fn conversation_tweet(tweet: &Tweet) -> &Tweet {
+    tweet.retweeted_status.as_deref().unwrap_or(tweet)
+}
+
+/// Draw the tweet in the detail panel, surrounded by its conversation if that was loaded.
+pub fn draw(
+    ctx: &mut crate::Context,
+    ui: &mut Ui,
+    tweet: &Tweet,
+    conversation: Option<&Conversation>,
+    read_only: bool,
+) -> Option<Action> {
+    let conversation = match conversation {
+        Some(conversation) if conversation.id == conversation_tweet(tweet).id => conversation,
+        _ => {
+            let mut action = None;
+            if ui.add(ClickableLink::new("Show conversation")).clicked() {
+                action = Some(Action::ShowConversation(conversation_tweet(tweet).id));
+            }
+            ui.separator();
+            return detail::draw_tweet(ctx, ui, tweet, read_only).or(action);
+        }
+    };
+    let mut action = None;
+    for ancestor in &conversation.ancestors {
+        action = detail::draw_tweet(ctx, ui, ancestor, read_only).or(action);
+        ui.separator();
+    }
+    let stroke = ui.visuals().selection.stroke;
+    Frame::group(ui.style()).stroke(stroke).show(ui, |ui| {
+        let tweet = conversation_tweet(tweet);
+        action = detail::draw_tweet(ctx, ui, tweet, read_only).or(action.take());
+    });
+    if conversation.loading {
+        ui.label("Loading conversation...");
+    } else if !conversation.replies.is_empty() {
+        ui.separator();
+        ui.label(RichText::new("Replies").strong());
+        for reply in &conversation.replies {
+            ui.separator();
+            action = detail::draw_tweet(ctx, ui, reply, read_only).or(action);
+        }
+    }
+    action
+}
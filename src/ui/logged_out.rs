@@ -1,11 +1,27 @@
-use crate::background::ToUI;
+use super::utils::ClickableLink;
+use crate::background::{twitter::LoginError, ToUI};
 use egui::*;
 
 #[derive(Default)]
 pub struct LoggedOut {
     pin: String,
-    error: Option<String>,
+    pub error: Option<String>,
+    login_error: Option<LoginError>,
     loading: bool,
+    /// The login page was opened in the browser
+    waiting_for_browser: bool,
+    authorize_url: String,
+    browser_opened: bool,
+    /// Accounts that are still logged in, when we're adding another account
+    accounts: Vec<String>,
+    consumer_missing: bool,
+    /// `Some(exists)` if the credentials have to be unlocked before logging in
+    credentials_locked: Option<bool>,
+    passphrase: String,
+    /// The user asked to forget their saved logins, and has to confirm it
+    confirm_forget: bool,
+    consumer_key: String,
+    consumer_secret: String,
 }
 
 impl LoggedOut {
@@ -16,23 +32,160 @@ impl LoggedOut {
         }
     }
 
+    pub fn with_accounts(accounts: Vec<String>) -> Self {
+        Self {
+            accounts,
+            ..Default::default()
+        }
+    }
+
     pub fn update(&mut self, msg: ToUI) {
         match msg {
             ToUI::Error { error } => {
                 self.error = Some(error);
+                self.loading = false;
+            }
+            ToUI::Loading => {
+                self.loading = true;
+            }
+            ToUI::Authorizing {
+                url,
+                browser_opened,
+            } => {
+                self.loading = false;
+                self.waiting_for_browser = true;
+                self.authorize_url = url;
+                self.browser_opened = browser_opened;
+            }
+            ToUI::LoginError { error } => {
+                self.loading = false;
+                if error.restarts_login() {
+                    self.waiting_for_browser = false;
+                }
+                self.login_error = Some(error);
+            }
+            ToUI::CredentialsLocked { exists } => {
+                self.credentials_locked = Some(exists);
+                self.confirm_forget = false;
+            }
+            ToUI::CredentialsUnlocked => {
+                self.credentials_locked = None;
+                self.error = None;
+            }
+            ToUI::ConsumerMissing => {
+                self.consumer_missing = true;
+                self.loading = false;
             }
-            ToUI::Loading => {}
             x => log::warn!(target: "UI", "Ignoring {:?}", x),
         }
     }
 
+    fn start_login(&mut self, ctx: &mut crate::Context) {
+        self.error = None;
+        self.login_error = None;
+        self.loading = true;
+        ctx.background.open_twitter_login();
+    }
+
+    fn enter_pin(&mut self, ctx: &mut crate::Context) {
+        self.error = None;
+        self.login_error = None;
+        self.loading = true;
+        ctx.background.enter_twitter_pin(self.pin.clone());
+    }
+
+    fn cancel_login(&mut self, ctx: &mut crate::Context) {
+        self.login_error = None;
+        self.loading = false;
+        self.waiting_for_browser = false;
+        self.pin.clear();
+        ctx.background.cancel_login();
+    }
+
+    /// Lets users who forgot their passphrase start over, after confirming it
+    fn draw_forget(&mut self, ui: &mut Ui, ctx: &mut crate::Context) {
+        if !self.confirm_forget {
+            if ui.button("Forgot your passphrase?").clicked() {
+                self.confirm_forget = true;
+            }
+            return;
+        }
+        ui.label("This deletes the saved logins of all accounts, you will have to log in again.");
+        ui.horizontal(|ui| {
+            if ui.button("Forget saved logins").clicked() {
+                self.error = None;
+                self.passphrase.clear();
+                ctx.background.forget_credentials();
+            }
+            if ui.button("Cancel").clicked() {
+                self.confirm_forget = false;
+            }
+        });
+    }
+
     pub fn draw(&mut self, ctx: &mut crate::Context) {
         CentralPanel::default().show(ctx.ctx, |ui| {
+            if let Some(exists) = self.credentials_locked {
+                if exists {
+                    ui.label("Enter your passphrase to unlock your saved logins");
+                } else {
+                    ui.label("Choose a passphrase to encrypt your saved logins");
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Passphrase: ");
+                    ui.add(TextEdit::singleline(&mut self.passphrase).password(true));
+                    if ui
+                        .add_enabled(
+                            !self.passphrase.is_empty(),
+                            Button::new(if exists { "Unlock" } else { "Save" }),
+                        )
+                        .clicked()
+                    {
+                        self.error = None;
+                        ctx.background
+                            .unlock_credentials(std::mem::take(&mut self.passphrase));
+                    }
+                });
+                if exists {
+                    self.draw_forget(ui, ctx);
+                }
+                ui.separator();
+            }
             if ui
-                .add_enabled(!self.loading, Button::new("Login (opens in browser)"))
+                .add_enabled(
+                    !self.loading && !self.consumer_missing && self.credentials_locked.is_none(),
+                    Button::new("Login (opens in browser)"),
+                )
                 .clicked()
             {
-                ctx.background.open_twitter_login();
+                self.start_login(ctx);
+            }
+            if ui
+                .add_enabled(
+                    !self.loading && !self.consumer_missing,
+                    Button::new("Browse without logging in"),
+                )
+                .clicked()
+            {
+                self.error = None;
+                ctx.background.browse_as_guest();
+            }
+            if self.waiting_for_browser {
+                if self.browser_opened {
+                    ui.label("Log in with your browser, you will be logged in automatically.");
+                } else {
+                    ui.label("Could not open your browser, please open this link to log in:");
+                    ui.horizontal(|ui| {
+                        ui.add(TextEdit::singleline(&mut self.authorize_url.as_str()));
+                        if ui.button("Copy").clicked() {
+                            ui.output().copied_text = self.authorize_url.clone();
+                        }
+                    });
+                }
+                ui.label("If twitter shows you a PIN instead, enter it below.");
+                if ui.button("Cancel").clicked() {
+                    self.cancel_login(ctx);
+                }
             }
             ui.horizontal(|ui| {
                 ui.label("Pin: ");
@@ -44,14 +197,70 @@ impl LoggedOut {
                     )
                     .clicked()
                 {
-                    self.error = None;
-                    self.loading = true;
-                    ctx.background.enter_twitter_pin(self.pin.clone());
+                    self.enter_pin(ctx);
                 }
             });
+            if let Some(error) = &self.login_error {
+                ui.label(error.to_string());
+                let restarts_login = error.restarts_login();
+                ui.horizontal(|ui| {
+                    if ui.button("Retry").clicked() {
+                        if restarts_login || self.pin.is_empty() {
+                            self.start_login(ctx);
+                        } else {
+                            self.enter_pin(ctx);
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.cancel_login(ctx);
+                    }
+                });
+            }
             if let Some(error) = &self.error {
                 ui.label(error);
             }
+            CollapsingHeader::new("API keys")
+                .default_open(self.consumer_missing)
+                .show(ui, |ui| {
+                    if self.consumer_missing {
+                        ui.label("Enter the consumer key pair of your twitter app to log in");
+                    }
+                    Grid::new("consumer_keys").show(ui, |ui| {
+                        ui.label("API key: ");
+                        ui.text_edit_singleline(&mut self.consumer_key);
+                        ui.end_row();
+                        ui.label("API secret: ");
+                        ui.add(TextEdit::singleline(&mut self.consumer_secret).password(true));
+                        ui.end_row();
+                    });
+                    if ui
+                        .add_enabled(
+                            !self.consumer_key.is_empty() && !self.consumer_secret.is_empty(),
+                            Button::new("Save"),
+                        )
+                        .clicked()
+                    {
+                        self.consumer_missing = false;
+                        ctx.background.set_consumer(
+                            std::mem::take(&mut self.consumer_key),
+                            std::mem::take(&mut self.consumer_secret),
+                        );
+                    }
+                });
+            if !self.accounts.is_empty() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Back to: ");
+                    for account in &self.accounts {
+                        if ui
+                            .add(ClickableLink::new(format!("@{}", account)))
+                            .clicked()
+                        {
+                            ctx.background.switch_account(account.clone());
+                        }
+                    }
+                });
+            }
         });
     }
 }
@@ -47,22 +47,37 @@ impl egui_with_background::App for State {
                     "Lost connection to server",
                 )));
             }
-            (ToUI::LoggedIn { user }, x) => {
-                let logged_in = LoggedIn::new(user, background);
+            (
+                ToUI::LoggedIn {
+                    user,
+                    accounts,
+                    columns,
+                    prefetch_threshold,
+                },
+                x,
+            ) => {
+                let logged_in =
+                    LoggedIn::new(user, accounts, columns, prefetch_threshold, background);
                 *x = TwitterState::LoggedIn(logged_in);
             }
+            (ToUI::LoggedOut { accounts, message }, x) => {
+                let mut logged_out = LoggedOut::with_accounts(accounts);
+                logged_out.error = message;
+                *x = TwitterState::LoggedOut(logged_out);
+            }
             (msg, TwitterState::LoggedOut(state)) => state.update(msg),
             (msg, TwitterState::LoggedIn(state)) => state.update(background, msg),
         }
     }
 
     fn key_pressed(&mut self, background: &mut Background, keycode: VirtualKeyCode) {
-        if keycode == VirtualKeyCode::Escape {
+        let handled = match &mut self.state {
+            TwitterState::LoggedIn(state) => state.key_pressed(background, keycode),
+            TwitterState::LoggedOut(_) => false,
+        };
+        if keycode == VirtualKeyCode::Escape && !handled {
             self.running = false;
         }
-        if let TwitterState::LoggedIn(state) = &mut self.state {
-            state.key_pressed(background, keycode);
-        }
     }
 
     fn key_released(&mut self, _background: &mut Background, _keycode: VirtualKeyCode) {}
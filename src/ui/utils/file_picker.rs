@@ -0,0 +1,134 @@
+use egui::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A window to pick a file on disk with. Only directories and files with one of the given
+/// extensions are listed.
+pub struct FilePicker {
+    title: &'static str,
+    extensions: &'static [&'static str],
+    directory: PathBuf,
+    entries: Vec<Entry>,
+    error: Option<String>,
+}
+
+struct Entry {
+    path: PathBuf,
+    name: String,
+    is_dir: bool,
+}
+
+pub enum FilePickerAction {
+    Pick(PathBuf),
+    Close,
+}
+
+impl FilePicker {
+    pub fn new(
+        title: &'static str,
+        extensions: &'static [&'static str],
+        directory: Option<&Path>,
+    ) -> Self {
+        let directory = directory
+            .map(Path::to_path_buf)
+            .or_else(home_dir)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        let mut result = Self {
+            title,
+            extensions,
+            directory: PathBuf::new(),
+            entries: Vec::new(),
+            error: None,
+        };
+        result.open(directory);
+        result
+    }
+
+    fn open(&mut self, directory: PathBuf) {
+        match fs::read_dir(&directory) {
+            Ok(read_dir) => {
+                let mut entries: Vec<Entry> = read_dir
+                    .filter_map(|entry| {
+                        let entry = entry.ok()?;
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        let path = entry.path();
+                        // Follow symlinks, so linked directories can be opened
+                        let is_dir = path.is_dir();
+                        if name.starts_with('.') || !(is_dir || self.has_extension(&path)) {
+                            return None;
+                        }
+                        Some(Entry { path, name, is_dir })
+                    })
+                    .collect();
+                entries.sort_by_key(|e| (!e.is_dir, e.name.to_lowercase()));
+                self.entries = entries;
+                self.directory = directory;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Could not open {}: {}", directory.display(), e)),
+        }
+    }
+
+    fn has_extension(&self, path: &Path) -> bool {
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        matches!(extension, Some(e) if self.extensions.contains(&e.as_str()))
+    }
+
+    pub fn draw(&mut self, ctx: &mut crate::Context) -> Option<FilePickerAction> {
+        let mut action = None;
+        let mut open = None;
+        Window::new(self.title)
+            .id(Id::new("file_picker"))
+            .collapsible(false)
+            .show(ctx.ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let parent = self.directory.parent();
+                    if ui
+                        .add_enabled(parent.is_some(), Button::new("Up"))
+                        .clicked()
+                    {
+                        open = parent.map(Path::to_path_buf);
+                    }
+                    ui.label(self.directory.display().to_string());
+                });
+                ui.separator();
+                ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                    for entry in &self.entries {
+                        if entry.is_dir {
+                            if ui
+                                .selectable_label(false, format!("{}/", entry.name))
+                                .clicked()
+                            {
+                                open = Some(entry.path.clone());
+                            }
+                        } else if ui.selectable_label(false, &entry.name).clicked() {
+                            action = Some(FilePickerAction::Pick(entry.path.clone()));
+                        }
+                    }
+                    if self.entries.is_empty() {
+                        ui.label(RichText::new("No matching files").weak());
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                ui.separator();
+                if ui.button("Cancel").clicked() {
+                    action = Some(FilePickerAction::Close);
+                }
+            });
+        if let Some(directory) = open {
+            self.open(directory);
+        }
+        action
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
@@ -1,30 +1,70 @@
-use crate::background::Background;
-use egui::{Vec2, Widget};
+use crate::background::{Background, Preview};
+use egui::{vec2, Sense, Shape, Vec2, Widget};
 use egui_with_background::image::{self, Key, LoadContext};
 
-pub struct Image {
-    context: LoadContext,
+pub struct Image<'a> {
+    source: Source<'a>,
     size: Vec2,
 }
 
-impl Image {
+enum Source<'a> {
+    /// Loaded into a texture by the background
+    Texture(LoadContext),
+    /// Decoded from a local file. Textures can only be made of images that are loaded by url, so
+    /// these are painted with the mesh the background built for them.
+    Pixels(&'a Preview),
+}
+
+impl Image<'static> {
     pub fn https(bg: &Background, url: impl Into<String>, size: impl Into<Vec2>) -> Self {
         let context = image::get_context(bg, Key::Https(url.into()));
         Self {
-            context,
+            source: Source::Texture(context),
             size: size.into(),
         }
     }
 }
 
-impl Widget for Image {
+impl<'a> Image<'a> {
+    /// Show a preview, scaled to fit in a square of the given size
+    pub fn preview(preview: &'a Preview, max_size: f32) -> Self {
+        let scale = max_size / preview.width.max(preview.height).max(1) as f32;
+        Self {
+            source: Source::Pixels(preview),
+            size: vec2(preview.width as f32, preview.height as f32) * scale,
+        }
+    }
+}
+
+impl Widget for Image<'_> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        if let Some(id) = self.context.get_texture_id() {
+        let context = match self.source {
+            Source::Texture(context) => context,
+            Source::Pixels(preview) => return paint_pixels(ui, preview, self.size),
+        };
+        if let Some(id) = context.get_texture_id() {
             egui::Image::new(id, self.size).ui(ui)
-        } else if let Some(msg) = self.context.get_error() {
+        } else if let Some(msg) = context.get_error() {
             egui::Label::new(msg).ui(ui)
         } else {
             egui::Label::new("").ui(ui)
         }
     }
 }
+
+fn paint_pixels(ui: &mut egui::Ui, preview: &Preview, size: Vec2) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+    if preview.width == 0 || !ui.is_rect_visible(rect) {
+        return response;
+    }
+    let scale = vec2(
+        size.x / preview.width as f32,
+        size.y / preview.height as f32,
+    );
+    let mut mesh = preview.mesh.clone();
+    for vertex in &mut mesh.vertices {
+        vertex.pos = rect.min + vertex.pos.to_vec2() * scale;
+    }
+    ui.painter().add(Shape::Mesh(mesh));
+    response
+}
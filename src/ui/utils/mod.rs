@@ -1,4 +1,9 @@
 mod clickable_link;
+mod file_picker;
 mod image;
 
-pub use self::{clickable_link::ClickableLink, image::Image};
+pub use self::{
+    clickable_link::ClickableLink,
+    file_picker::{FilePicker, FilePickerAction},
+    image::Image,
+};